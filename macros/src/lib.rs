@@ -0,0 +1,163 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use syn::{parse_macro_input, ItemFn, LitStr};
+
+#[derive(Default)]
+struct MigrationFiles {
+    name: String,
+    up: Option<PathBuf>,
+    down: Option<PathBuf>,
+}
+
+/// Embeds a directory of `NNN_name.up.sql` / `NNN_name.down.sql` files into
+/// the binary, expanding to a `Vec<Migration>` ordered by number. The path is
+/// relative to the directory containing the invoking crate's `Cargo.toml`.
+/// Every up file must have a matching down file.
+///
+/// Changes to the contents of existing files cause a rebuild, but adding a
+/// new file does not. Touch the file invoking the macro after adding a
+/// migration.
+#[proc_macro]
+pub fn include_migrations(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    match read_migrations(&path.value()) {
+        Ok(migrations) => {
+            let migrations = migrations.into_iter().map(|files| {
+                let name = files.name;
+                let up = files.up.unwrap().to_string_lossy().into_owned();
+                let down = files.down.unwrap().to_string_lossy().into_owned();
+                quote! {
+                    ::sqlx_simple_migrator::Migration::new(#name)
+                        .with_up(::std::include_str!(#up))
+                        .with_down(::std::include_str!(#down))
+                }
+            });
+            quote!(::std::vec![#(#migrations),*]).into()
+        }
+        Err(message) => syn::Error::new(path.span(), message)
+            .to_compile_error()
+            .into(),
+    }
+}
+
+/// Registers the annotated `fn() -> Migration` so that it is included in
+/// `Migration::registered()`, without listing it in a `Vec` by hand. The
+/// function is called before `main` to register it, so it works across
+/// modules and crates, as long as the crate defining it is linked into the
+/// binary.
+#[proc_macro_attribute]
+pub fn register_migration(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+    if !function.sig.inputs.is_empty() || !function.sig.generics.params.is_empty() {
+        return syn::Error::new_spanned(
+            &function.sig,
+            "#[register_migration] requires a `fn() -> Migration`",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let name = &function.sig.ident;
+    quote! {
+        #function
+
+        const _: () = {
+            #[used]
+            #[cfg_attr(
+                any(
+                    target_os = "linux",
+                    target_os = "android",
+                    target_os = "freebsd",
+                    target_os = "netbsd",
+                    target_os = "openbsd",
+                    target_os = "dragonfly",
+                    target_os = "illumos",
+                ),
+                link_section = ".init_array"
+            )]
+            #[cfg_attr(target_vendor = "apple", link_section = "__DATA,__mod_init_func")]
+            #[cfg_attr(windows, link_section = ".CRT$XCU")]
+            static REGISTER: extern "C" fn() = {
+                extern "C" fn register() {
+                    ::sqlx_simple_migrator::registry::register(#name);
+                }
+                register
+            };
+        };
+    }
+    .into()
+}
+
+fn read_migrations(path: &str) -> Result<Vec<MigrationFiles>, String> {
+    let root = std::env::var("CARGO_MANIFEST_DIR").map_err(|err| err.to_string())?;
+    let dir = Path::new(&root).join(path);
+    let entries = fs::read_dir(&dir)
+        .map_err(|err| format!("Error reading \"{}\": {}", dir.display(), err))?;
+
+    let mut files = BTreeMap::<u64, MigrationFiles>::new();
+    for entry in entries {
+        let path = entry.map_err(|err| err.to_string())?.path();
+        let file_name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(file_name) => file_name.to_owned(),
+            None => continue,
+        };
+        let (name, is_up) = if let Some(name) = file_name.strip_suffix(".up.sql") {
+            (name, true)
+        } else if let Some(name) = file_name.strip_suffix(".down.sql") {
+            (name, false)
+        } else {
+            continue;
+        };
+        let number = parse_number(name).ok_or_else(|| {
+            format!(
+                "\"{}\" is not named NNN_name.up.sql or NNN_name.down.sql",
+                file_name
+            )
+        })?;
+
+        let migration = files.entry(number).or_default();
+        if migration.name.is_empty() {
+            migration.name = name.to_owned();
+        } else if migration.name != name {
+            return Err(format!(
+                "Migration number {} is used by both \"{}\" and \"{}\"",
+                number, migration.name, name
+            ));
+        }
+        if is_up {
+            migration.up = Some(path);
+        } else {
+            migration.down = Some(path);
+        }
+    }
+
+    let mismatched = files
+        .values()
+        .filter_map(|files| match (&files.up, &files.down) {
+            (Some(_), Some(_)) => None,
+            (Some(_), None) => Some(format!("{}.down.sql", files.name)),
+            (None, _) => Some(format!("{}.up.sql", files.name)),
+        })
+        .collect::<Vec<_>>();
+    if !mismatched.is_empty() {
+        return Err(format!(
+            "Missing migration files: {}",
+            mismatched.join(", ")
+        ));
+    }
+
+    Ok(files.into_values().collect())
+}
+
+/// Parses the `NNN` from `NNN_name`
+fn parse_number(name: &str) -> Option<u64> {
+    let (number, name) = name.split_at(name.find('_')?);
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) || name.len() <= 1 {
+        return None;
+    }
+    number.parse().ok()
+}
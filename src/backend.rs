@@ -0,0 +1,700 @@
+/// Implements the query methods of `MigrationBackend` in terms of the
+/// backend's statement constants. The query code is identical for every
+/// backend, but can't be written generically without a wall of bounds.
+#[allow(unused_macros)]
+macro_rules! backend_queries {
+    () => {
+        fn execute<'c>(
+            conn: &'c mut Self::Connection,
+            statement: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<(), sqlx::Error>> {
+            // Executing the raw string rather than a prepared query allows a
+            // single statement to contain multiple sql commands.
+            Box::pin(async move {
+                sqlx::Executor::execute(conn, statement).await?;
+                Ok(())
+            })
+        }
+
+        fn execute_with_key<'c>(
+            conn: &'c mut Self::Connection,
+            statement: &'c str,
+            key: i64,
+        ) -> futures_core::future::BoxFuture<'c, Result<(), sqlx::Error>> {
+            Box::pin(async move {
+                sqlx::query(statement).bind(key).execute(conn).await?;
+                Ok(())
+            })
+        }
+
+        fn execute_with_values<'c>(
+            conn: &'c mut Self::Connection,
+            statement: &'c str,
+            values: &'c [&'c str],
+        ) -> futures_core::future::BoxFuture<'c, Result<(), sqlx::Error>> {
+            Box::pin(async move {
+                let mut query = sqlx::query(statement);
+                for &value in values {
+                    query = query.bind(value);
+                }
+                query.execute(conn).await?;
+                Ok(())
+            })
+        }
+
+        fn try_lock<'c>(
+            conn: &'c mut Self::Connection,
+            statement: &'c str,
+            key: i64,
+        ) -> futures_core::future::BoxFuture<'c, Result<bool, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                let acquired: Option<i64> = sqlx::query(statement)
+                    .bind(key)
+                    .fetch_one(conn)
+                    .await?
+                    .try_get(0)?;
+                Ok(acquired == Some(1))
+            })
+        }
+
+        fn performed_migrations<'c>(
+            conn: &'c mut Self::Connection,
+            table: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<Vec<(String, String)>, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                // Not cached, as a cached plan can't return the columns the
+                // crate's own migrations add
+                let rows = sqlx::query(&super::render(Self::SELECT_MIGRATIONS, table))
+                    .persistent(false)
+                    .fetch_all(conn)
+                    .await?;
+                rows.iter()
+                    .map(|row| {
+                        Ok((
+                            // Tables created before schemas were recorded
+                            // don't have the column until they're upgraded
+                            row.try_get("schema").unwrap_or_default(),
+                            row.try_get("name")?,
+                        ))
+                    })
+                    .collect()
+            })
+        }
+
+        fn applied_migrations<'c>(
+            conn: &'c mut Self::Connection,
+            table: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<Vec<super::AppliedRecord>, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                // Not cached, as a cached plan can't return the columns the
+                // crate's own migrations add
+                let rows = sqlx::query(&super::render(Self::SELECT_APPLIED_MIGRATIONS, table))
+                    .persistent(false)
+                    .fetch_all(conn)
+                    .await?;
+                rows.iter()
+                    .map(|row| {
+                        Ok((
+                            row.try_get("schema").unwrap_or_default(),
+                            row.try_get("name")?,
+                            row.try_get("executed_at")?,
+                            // Tables created before descriptions were recorded
+                            // don't have the column until they're upgraded
+                            row.try_get("description").ok().flatten(),
+                        ))
+                    })
+                    .collect()
+            })
+        }
+
+        fn select_history<'c>(
+            conn: &'c mut Self::Connection,
+            statement: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<Vec<super::HistoryRecord>, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                let rows = sqlx::query(statement).fetch_all(conn).await?;
+                rows.iter()
+                    .map(|row| {
+                        Ok((
+                            row.try_get("version")?,
+                            row.try_get("description")?,
+                            row.try_get("script")?,
+                        ))
+                    })
+                    .collect()
+            })
+        }
+
+        fn select_objects<'c>(
+            conn: &'c mut Self::Connection,
+            statement: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<Vec<(String, String)>, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                let rows = sqlx::query(statement).fetch_all(conn).await?;
+                rows.iter()
+                    .map(|row| Ok((row.try_get("name")?, row.try_get("kind")?)))
+                    .collect()
+            })
+        }
+
+        fn query_condition<'c>(
+            conn: &'c mut Self::Connection,
+            statement: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<bool, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                sqlx::query(statement).fetch_one(conn).await?.try_get(0)
+            })
+        }
+
+        fn query_bound<'c>(
+            conn: &'c mut Self::Connection,
+            statement: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<Option<i64>, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                sqlx::query(statement).fetch_one(conn).await?.try_get(0)
+            })
+        }
+
+        fn query_schema<'c>(
+            conn: &'c mut Self::Connection,
+            statement: &'c str,
+            values: &'c [&'c str],
+        ) -> futures_core::future::BoxFuture<'c, Result<Option<String>, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                let mut query = sqlx::query(statement);
+                for &value in values {
+                    query = query.bind(value);
+                }
+                match query.fetch_optional(conn).await? {
+                    Some(row) => row.try_get(0),
+                    None => Ok(None),
+                }
+            })
+        }
+
+        fn record_migration<'c>(
+            conn: &'c mut Self::Connection,
+            table: &'c str,
+            name: &'c str,
+            details: Option<super::MigrationDetails<'c>>,
+        ) -> futures_core::future::BoxFuture<'c, Result<(), sqlx::Error>> {
+            Box::pin(async move {
+                match details {
+                    Some(details) => {
+                        sqlx::query(&super::render(Self::INSERT_MIGRATION_WITH_DETAILS, table))
+                            .bind(name)
+                            .bind(details.checksum)
+                            .bind(details.duration_ms)
+                            .bind(details.statement_count)
+                            .bind(details.hostname)
+                            .bind(details.app_version)
+                            .bind(details.build_id)
+                            .bind(details.description)
+                            .bind(details.schema)
+                            .bind(details.batch)
+                            .bind(details.repeatable)
+                            .execute(conn)
+                            .await?
+                    }
+                    None => {
+                        sqlx::query(&super::render(Self::INSERT_MIGRATION, table))
+                            .bind(name)
+                            .execute(conn)
+                            .await?
+                    }
+                };
+                Ok(())
+            })
+        }
+
+        fn last_batch<'c>(
+            conn: &'c mut Self::Connection,
+            table: &'c str,
+            schema: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<Vec<String>, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                let rows = sqlx::query(&super::render(Self::SELECT_LAST_BATCH, table))
+                    .bind(schema)
+                    .bind(schema)
+                    .fetch_all(conn)
+                    .await?;
+                rows.iter().map(|row| row.try_get("name")).collect()
+            })
+        }
+
+        fn migration_checksum<'c>(
+            conn: &'c mut Self::Connection,
+            table: &'c str,
+            schema: &'c str,
+            name: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<Option<String>, sqlx::Error>> {
+            Box::pin(async move {
+                use sqlx::Row;
+                let row = sqlx::query(&super::render(Self::SELECT_CHECKSUM, table))
+                    .bind(name)
+                    .bind(schema)
+                    .fetch_optional(conn)
+                    .await?;
+                match row {
+                    Some(row) => row.try_get("checksum"),
+                    None => Ok(None),
+                }
+            })
+        }
+
+        fn update_checksum<'c>(
+            conn: &'c mut Self::Connection,
+            table: &'c str,
+            schema: &'c str,
+            name: &'c str,
+            checksum: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<(), sqlx::Error>> {
+            Box::pin(async move {
+                sqlx::query(&super::render(Self::UPDATE_CHECKSUM, table))
+                    .bind(checksum)
+                    .bind(name)
+                    .bind(schema)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn rename_migration<'c>(
+            conn: &'c mut Self::Connection,
+            table: &'c str,
+            schema: &'c str,
+            name: &'c str,
+            new_name: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<(), sqlx::Error>> {
+            Box::pin(async move {
+                sqlx::query(&super::render(Self::RENAME_MIGRATION, table))
+                    .bind(new_name)
+                    .bind(name)
+                    .bind(schema)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn remove_migration<'c>(
+            conn: &'c mut Self::Connection,
+            table: &'c str,
+            schema: Option<&'c str>,
+            name: &'c str,
+        ) -> futures_core::future::BoxFuture<'c, Result<(), sqlx::Error>> {
+            Box::pin(async move {
+                match schema {
+                    Some(schema) => {
+                        sqlx::query(&super::render(Self::DELETE_MIGRATION_WITH_SCHEMA, table))
+                            .bind(name)
+                            .bind(schema)
+                            .execute(conn)
+                            .await?
+                    }
+                    None => {
+                        sqlx::query(&super::render(Self::DELETE_MIGRATION, table))
+                            .bind(name)
+                            .execute(conn)
+                            .await?
+                    }
+                };
+                Ok(())
+            })
+        }
+    };
+}
+
+#[cfg(feature = "mysql")]
+mod mysql;
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use super::MigratorConfig;
+use futures_core::future::BoxFuture;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    Database,
+};
+use std::time::Duration;
+
+/// Replaces `{table}` within one of a backend's statements
+pub(crate) fn render(statement: &str, table: &str) -> String {
+    statement.replace("{table}", table)
+}
+
+/// Returns the statements that apply the configured timeouts at the start of
+/// a migration's transaction
+pub(crate) fn timeout_statements<DB: MigrationBackend>(config: &MigratorConfig) -> Vec<String> {
+    let render_timeout = |statement: &str, timeout: Duration| {
+        // Round up, so that a timeout shorter than a second isn't disabled by
+        // a backend that only accepts whole seconds
+        let seconds = timeout.as_millis().div_ceil(1000);
+        statement
+            .replace("{milliseconds}", &timeout.as_millis().to_string())
+            .replace("{seconds}", &seconds.to_string())
+    };
+    let mut statements = Vec::new();
+    if let (Some(statement), Some(timeout)) = (DB::SET_STATEMENT_TIMEOUT, config.statement_timeout)
+    {
+        statements.push(render_timeout(statement, timeout));
+    }
+    if let (Some(statement), Some(timeout)) = (DB::SET_LOCK_TIMEOUT, config.lock_timeout) {
+        statements.push(render_timeout(statement, timeout));
+    }
+    statements
+}
+
+/// The schema, name, execution time and description of a performed
+/// migration
+pub type AppliedRecord = (String, String, DateTime<Utc>, Option<String>);
+
+/// The version, description and script of an entry in another migration
+/// tool's history
+pub type HistoryRecord = (String, Option<String>, Option<String>);
+
+#[derive(Clone, Copy, Debug)]
+/// The details recorded for a migration performed after the built-in
+/// migrations
+pub struct MigrationDetails<'a> {
+    pub checksum: &'a str,
+    /// How long the migration took to execute, or `None` if it was recorded
+    /// without being executed
+    pub duration_ms: Option<i64>,
+    pub statement_count: i64,
+    /// The host name of the machine that performed the migration
+    pub hostname: Option<&'a str>,
+    /// The version of the application that performed the migration
+    pub app_version: Option<&'a str>,
+    /// The identifier of the build that performed the migration
+    pub build_id: Option<&'a str>,
+    pub description: Option<&'a str>,
+    /// The schema the migration is tracked for
+    pub schema: &'a str,
+    /// The run that performed the migration, or `None` if it was recorded
+    /// without being executed
+    pub batch: Option<i64>,
+    /// Whether the migration is executed again whenever it changes
+    pub repeatable: bool,
+}
+
+/// A database that migrations can be executed against. Each backend provides
+/// the statements used to maintain the migrations bookkeeping table. Within
+/// these statements, `{table}` is replaced with the configured table name.
+/// The query methods receive the table name already qualified with its schema.
+pub trait MigrationBackend: Database {
+    /// The statement that creates the migrations table if it doesn't exist,
+    /// so that processes migrating a new database at once don't conflict
+    const CREATE_MIGRATIONS_TABLE: &'static str;
+    /// The statement that drops the migrations table
+    const DROP_MIGRATIONS_TABLE: &'static str;
+    /// The query that returns every column of all performed migrations, so
+    /// that their `schema` can be read once the column exists
+    const SELECT_MIGRATIONS: &'static str;
+    /// The query that returns the name, `executed_at` and `description` of
+    /// all performed migrations, in the order they were executed
+    const SELECT_APPLIED_MIGRATIONS: &'static str;
+    /// The statement that records one of the crate's own migrations as
+    /// performed, binding the name. Like the statement below, it does nothing
+    /// if the migration is already recorded.
+    const INSERT_MIGRATION: &'static str;
+    /// The statement that records a migration as performed, binding the name,
+    /// checksum, duration in milliseconds, statement count, host name,
+    /// application version, build identifier, description, schema, batch and
+    /// whether it's repeatable. The
+    /// database user is recorded as `applied_by` where the database has users.
+    const INSERT_MIGRATION_WITH_DETAILS: &'static str;
+    /// The statement that removes the record of one of the crate's own
+    /// migrations, binding the name
+    const DELETE_MIGRATION: &'static str;
+    /// The statement that removes a migration's record, binding the name and
+    /// schema
+    const DELETE_MIGRATION_WITH_SCHEMA: &'static str;
+    /// The statement that adds the `checksum` column to the migrations table
+    const ADD_CHECKSUM_COLUMN: &'static str;
+    /// The statement that drops the `checksum` column from the migrations table
+    const DROP_CHECKSUM_COLUMN: &'static str;
+    /// The statement that adds the `duration_ms` and `statement_count`
+    /// columns to the migrations table
+    const ADD_STATISTICS_COLUMNS: &'static str;
+    /// The statement that drops the `duration_ms` and `statement_count`
+    /// columns from the migrations table
+    const DROP_STATISTICS_COLUMNS: &'static str;
+    /// The statement that adds the `applied_by`, `hostname` and
+    /// `app_version` columns to the migrations table
+    const ADD_APPLIED_BY_COLUMNS: &'static str;
+    /// The statement that drops the `applied_by`, `hostname` and
+    /// `app_version` columns from the migrations table
+    const DROP_APPLIED_BY_COLUMNS: &'static str;
+    /// The statement that adds the `build_id` column to the migrations table
+    const ADD_BUILD_ID_COLUMN: &'static str;
+    /// The statement that drops the `build_id` column from the migrations table
+    const DROP_BUILD_ID_COLUMN: &'static str;
+    /// The statement that adds the `description` column to the migrations
+    /// table
+    const ADD_DESCRIPTION_COLUMN: &'static str;
+    /// The statement that drops the `description` column from the migrations
+    /// table
+    const DROP_DESCRIPTION_COLUMN: &'static str;
+    /// The statement that adds the `schema` column to the migrations table,
+    /// defaulting to an empty string, and makes `schema` and `name` its
+    /// primary key
+    const ADD_SCHEMA_COLUMN: &'static str;
+    /// The statement that removes the records of migrations tracked for a
+    /// schema, then drops the `schema` column from the migrations table,
+    /// making `name` its primary key again
+    const DROP_SCHEMA_COLUMN: &'static str;
+    /// The statement that adds the `batch` column to the migrations table
+    const ADD_BATCH_COLUMN: &'static str;
+    /// The statement that drops the `batch` column from the migrations table
+    const DROP_BATCH_COLUMN: &'static str;
+    /// The statement that adds the `repeatable` column to the migrations
+    /// table
+    const ADD_REPEATABLE_COLUMN: &'static str;
+    /// The statement that drops the `repeatable` column from the migrations
+    /// table
+    const DROP_REPEATABLE_COLUMN: &'static str;
+    /// The query that returns the batch of the next run as a 64-bit integer,
+    /// one more than the largest batch recorded
+    const SELECT_NEXT_BATCH: &'static str;
+    /// The query that returns the `name` of the migrations in the last batch
+    /// recorded for a schema, binding the schema twice
+    const SELECT_LAST_BATCH: &'static str;
+    /// The query that returns a migration's checksum, binding the name and
+    /// schema
+    const SELECT_CHECKSUM: &'static str;
+    /// The statement that updates a migration's checksum, binding the
+    /// checksum, name and schema
+    const UPDATE_CHECKSUM: &'static str;
+    /// The statement that renames a migration's record, binding the new name,
+    /// the current name and the schema
+    const RENAME_MIGRATION: &'static str;
+    /// The query that returns the `version`, `description` and `script` of
+    /// the migrations sqlx-cli has successfully applied, as text
+    const SELECT_SQLX_MIGRATIONS: &'static str;
+    /// The query that returns the `version`, `description` and `script` of
+    /// the versioned migrations Flyway has successfully applied, as text
+    const SELECT_FLYWAY_MIGRATIONS: &'static str;
+    /// The query that returns the `version`, `description` and `script` of
+    /// the migrations Diesel has applied, as text
+    const SELECT_DIESEL_MIGRATIONS: &'static str;
+    /// The query that returns the largest `{key}` of the next batch of a
+    /// backfill as a 64-bit integer, or `NULL` when no rows are left
+    const SELECT_BACKFILL_BOUND: &'static str;
+    /// The statement that creates the `{table}` of checkpoints if it doesn't
+    /// exist
+    const CREATE_CHECKPOINTS_TABLE: &'static str;
+    /// The query that returns a checkpoint's position, binding its name
+    const SELECT_CHECKPOINT: &'static str;
+    /// The statement that inserts or replaces a checkpoint's position,
+    /// binding its name and position
+    const SAVE_CHECKPOINT: &'static str;
+    /// The statement that removes a checkpoint, binding its name
+    const DELETE_CHECKPOINT: &'static str;
+    /// The statement that updates the planner's statistics for `{table}`,
+    /// one of the tables a migration touched
+    const ANALYZE_TABLE: &'static str;
+    /// The query that returns the name of the table named by the bound value,
+    /// if it exists
+    const SELECT_TABLE: &'static str;
+    /// The query that returns the type of the column named by the bound
+    /// table and column names, if it exists
+    const SELECT_COLUMN_TYPE: &'static str;
+    /// The statement that waits for and acquires an advisory lock, binding
+    /// the lock key. Backends without advisory locks leave this unset.
+    const ACQUIRE_LOCK: Option<&'static str> = None;
+    /// The query that acquires an advisory lock without waiting, binding the
+    /// lock key and returning 1 if the lock was acquired
+    const TRY_ACQUIRE_LOCK: Option<&'static str> = None;
+    /// The statement that releases an advisory lock, binding the lock key
+    const RELEASE_LOCK: Option<&'static str> = None;
+    /// Statements executed on the connection before each migration's
+    /// transaction begins
+    const BEFORE_MIGRATION: &'static [&'static str] = &[];
+    /// Statements executed on the connection after each migration's
+    /// transaction has been committed or rolled back
+    const AFTER_MIGRATION: &'static [&'static str] = &[];
+    /// The statement that limits how long each statement may run within a
+    /// migration's transaction. `{milliseconds}` or `{seconds}` is replaced
+    /// with the timeout. Backends without statement timeouts leave this unset.
+    const SET_STATEMENT_TIMEOUT: Option<&'static str> = None;
+    /// The statement that limits how long a statement may wait for a lock
+    /// within a migration's transaction, like `SET_STATEMENT_TIMEOUT`
+    const SET_LOCK_TIMEOUT: Option<&'static str> = None;
+    /// The statement that sets the isolation level of the current
+    /// transaction, executed before any other statement in it. `{level}` is
+    /// replaced with the level, such as `SERIALIZABLE`.
+    const SET_ISOLATION_LEVEL: Option<&'static str> = None;
+    /// The error codes of failures that may succeed when retried, such as
+    /// deadlocks and serialization failures
+    const TRANSIENT_ERROR_CODES: &'static [&'static str] = &[];
+    /// The error codes returned when querying a table that doesn't exist
+    const UNDEFINED_TABLE_ERROR_CODES: &'static [&'static str] = &[];
+    /// The statement that makes `{schema}` the connection's search path.
+    /// Backends without schemas leave this unset.
+    const SET_SEARCH_PATH: Option<&'static str> = None;
+    /// The statement that restores the connection's default search path
+    const RESET_SEARCH_PATH: Option<&'static str> = None;
+    /// The statement that makes `{schema}` the search path until the end of
+    /// the current transaction
+    const SET_LOCAL_SEARCH_PATH: Option<&'static str> = None;
+    /// The statement that creates the empty schema `{schema}`
+    const CREATE_SCHEMA: Option<&'static str> = None;
+    /// The statement that drops the schema `{schema}` and everything in it
+    const DROP_SCHEMA: Option<&'static str> = None;
+    /// The query that returns the schema unqualified tables are created in
+    const SELECT_CURRENT_SCHEMA: Option<&'static str> = None;
+    /// The query that returns the name of the bound extension if the server
+    /// can install it. Backends without extensions leave this unset.
+    const SELECT_AVAILABLE_EXTENSION: Option<&'static str> = None;
+    /// The query listing the qualified `name` and `kind`, `TABLE` or
+    /// `SEQUENCE`, of the objects in the current schemas. Backends without
+    /// grants leave this unset.
+    const SELECT_OBJECTS: Option<&'static str> = None;
+    /// The statement that notifies listeners on a channel, binding the
+    /// channel and the payload. Backends without notifications leave this
+    /// unset.
+    const NOTIFY: Option<&'static str> = None;
+
+    /// Execute a single sql statement
+    fn execute<'c>(
+        conn: &'c mut Self::Connection,
+        statement: &'c str,
+    ) -> BoxFuture<'c, Result<(), sqlx::Error>>;
+
+    /// Execute a single sql statement, binding `key`
+    fn execute_with_key<'c>(
+        conn: &'c mut Self::Connection,
+        statement: &'c str,
+        key: i64,
+    ) -> BoxFuture<'c, Result<(), sqlx::Error>>;
+
+    /// Execute a single sql statement, binding each of `values`
+    fn execute_with_values<'c>(
+        conn: &'c mut Self::Connection,
+        statement: &'c str,
+        values: &'c [&'c str],
+    ) -> BoxFuture<'c, Result<(), sqlx::Error>>;
+
+    /// Returns true if `statement`, binding `key`, acquired an advisory lock
+    fn try_lock<'c>(
+        conn: &'c mut Self::Connection,
+        statement: &'c str,
+        key: i64,
+    ) -> BoxFuture<'c, Result<bool, sqlx::Error>>;
+
+    /// Returns the schema and name of all migrations that have been performed.
+    /// The schema is empty for tables without the `schema` column.
+    fn performed_migrations<'c>(
+        conn: &'c mut Self::Connection,
+        table: &'c str,
+    ) -> BoxFuture<'c, Result<Vec<(String, String)>, sqlx::Error>>;
+
+    /// Returns the schema, name, execution time and description of all
+    /// migrations that have been performed, in the order they were executed
+    fn applied_migrations<'c>(
+        conn: &'c mut Self::Connection,
+        table: &'c str,
+    ) -> BoxFuture<'c, Result<Vec<AppliedRecord>, sqlx::Error>>;
+
+    /// Returns the `version`, `description` and `script` columns of
+    /// `statement`, which reads another migration tool's history
+    fn select_history<'c>(
+        conn: &'c mut Self::Connection,
+        statement: &'c str,
+    ) -> BoxFuture<'c, Result<Vec<HistoryRecord>, sqlx::Error>>;
+
+    /// Returns the `name` and `kind` columns of `statement`, which lists the
+    /// objects in the current schemas
+    fn select_objects<'c>(
+        conn: &'c mut Self::Connection,
+        statement: &'c str,
+    ) -> BoxFuture<'c, Result<Vec<(String, String)>, sqlx::Error>>;
+
+    /// Returns the boolean returned by `statement`, a migration's condition
+    fn query_condition<'c>(
+        conn: &'c mut Self::Connection,
+        statement: &'c str,
+    ) -> BoxFuture<'c, Result<bool, sqlx::Error>>;
+
+    /// Returns the integer returned by `statement`, such as a backfill's next
+    /// bound
+    fn query_bound<'c>(
+        conn: &'c mut Self::Connection,
+        statement: &'c str,
+    ) -> BoxFuture<'c, Result<Option<i64>, sqlx::Error>>;
+
+    /// Returns the text returned by `statement`, such as one of the schema
+    /// queries, binding `values`
+    fn query_schema<'c>(
+        conn: &'c mut Self::Connection,
+        statement: &'c str,
+        values: &'c [&'c str],
+    ) -> BoxFuture<'c, Result<Option<String>, sqlx::Error>>;
+
+    /// Record that the migration `name` has been performed
+    fn record_migration<'c>(
+        conn: &'c mut Self::Connection,
+        table: &'c str,
+        name: &'c str,
+        details: Option<MigrationDetails<'c>>,
+    ) -> BoxFuture<'c, Result<(), sqlx::Error>>;
+
+    /// Returns the names of the migrations in the last batch recorded for
+    /// `schema`
+    fn last_batch<'c>(
+        conn: &'c mut Self::Connection,
+        table: &'c str,
+        schema: &'c str,
+    ) -> BoxFuture<'c, Result<Vec<String>, sqlx::Error>>;
+
+    /// Returns the checksum recorded for the migration `name` of `schema`, if
+    /// any. A migration without a record, such as one `dry_run` expects to be
+    /// renamed or adopted, has no checksum.
+    fn migration_checksum<'c>(
+        conn: &'c mut Self::Connection,
+        table: &'c str,
+        schema: &'c str,
+        name: &'c str,
+    ) -> BoxFuture<'c, Result<Option<String>, sqlx::Error>>;
+
+    /// Replace the checksum recorded for the migration `name` of `schema`
+    fn update_checksum<'c>(
+        conn: &'c mut Self::Connection,
+        table: &'c str,
+        schema: &'c str,
+        name: &'c str,
+        checksum: &'c str,
+    ) -> BoxFuture<'c, Result<(), sqlx::Error>>;
+
+    /// Rename the record of the migration `name` of `schema` to `new_name`
+    fn rename_migration<'c>(
+        conn: &'c mut Self::Connection,
+        table: &'c str,
+        schema: &'c str,
+        name: &'c str,
+        new_name: &'c str,
+    ) -> BoxFuture<'c, Result<(), sqlx::Error>>;
+
+    /// Remove the record of the migration `name` being performed for
+    /// `schema`, or of one of the crate's own migrations when `schema` is
+    /// `None`
+    fn remove_migration<'c>(
+        conn: &'c mut Self::Connection,
+        table: &'c str,
+        schema: Option<&'c str>,
+        name: &'c str,
+    ) -> BoxFuture<'c, Result<(), sqlx::Error>>;
+}
@@ -0,0 +1,121 @@
+use super::MigrationBackend;
+use sqlx::MySql;
+
+/// MySQL implicitly commits most DDL statements, so a failing migration may
+/// leave earlier statements applied even though its transaction is rolled
+/// back.
+impl MigrationBackend for MySql {
+    const CREATE_MIGRATIONS_TABLE: &'static str = r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            name VARCHAR(255) NOT NULL PRIMARY KEY,
+            executed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#;
+    const DROP_MIGRATIONS_TABLE: &'static str = r#"
+        DROP TABLE IF EXISTS {table}
+        "#;
+    const SELECT_MIGRATIONS: &'static str = "SELECT * FROM {table}";
+    const SELECT_APPLIED_MIGRATIONS: &'static str =
+        "SELECT * FROM {table} ORDER BY executed_at, name";
+    const INSERT_MIGRATION: &'static str =
+        "INSERT INTO {table} (name) VALUES (?) ON DUPLICATE KEY UPDATE name = name";
+    const INSERT_MIGRATION_WITH_DETAILS: &'static str =
+        "INSERT INTO {table} (name, checksum, duration_ms, statement_count, applied_by, hostname, app_version, build_id, description, `schema`, batch, repeatable) VALUES (?, ?, ?, ?, CURRENT_USER(), ?, ?, ?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE name = name";
+    const DELETE_MIGRATION: &'static str = "DELETE FROM {table} WHERE name = ?";
+    const DELETE_MIGRATION_WITH_SCHEMA: &'static str =
+        "DELETE FROM {table} WHERE name = ? AND `schema` = ?";
+    const ADD_CHECKSUM_COLUMN: &'static str = "ALTER TABLE {table} ADD COLUMN checksum VARCHAR(64)";
+    const DROP_CHECKSUM_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN checksum";
+    const ADD_STATISTICS_COLUMNS: &'static str =
+        "ALTER TABLE {table} ADD COLUMN duration_ms BIGINT, ADD COLUMN statement_count BIGINT";
+    const DROP_STATISTICS_COLUMNS: &'static str =
+        "ALTER TABLE {table} DROP COLUMN duration_ms, DROP COLUMN statement_count";
+    const ADD_APPLIED_BY_COLUMNS: &'static str =
+        "ALTER TABLE {table} ADD COLUMN applied_by VARCHAR(255), ADD COLUMN hostname VARCHAR(255), ADD COLUMN app_version VARCHAR(255)";
+    const DROP_APPLIED_BY_COLUMNS: &'static str =
+        "ALTER TABLE {table} DROP COLUMN applied_by, DROP COLUMN hostname, DROP COLUMN app_version";
+    const ADD_BUILD_ID_COLUMN: &'static str =
+        "ALTER TABLE {table} ADD COLUMN build_id VARCHAR(255)";
+    const DROP_BUILD_ID_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN build_id";
+    const ADD_DESCRIPTION_COLUMN: &'static str = "ALTER TABLE {table} ADD COLUMN description TEXT";
+    const DROP_DESCRIPTION_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN description";
+    // `SCHEMA` is a reserved word in MySQL
+    const ADD_SCHEMA_COLUMN: &'static str =
+        "ALTER TABLE {table} ADD COLUMN `schema` VARCHAR(255) NOT NULL DEFAULT '', DROP PRIMARY KEY, ADD PRIMARY KEY (`schema`, name)";
+    const DROP_SCHEMA_COLUMN: &'static str = r#"
+        DELETE FROM {table} WHERE `schema` <> '';
+        ALTER TABLE {table} DROP PRIMARY KEY, DROP COLUMN `schema`, ADD PRIMARY KEY (name)
+        "#;
+    const ADD_BATCH_COLUMN: &'static str = "ALTER TABLE {table} ADD COLUMN batch BIGINT";
+    const DROP_BATCH_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN batch";
+    const ADD_REPEATABLE_COLUMN: &'static str =
+        "ALTER TABLE {table} ADD COLUMN repeatable BOOLEAN NOT NULL DEFAULT FALSE";
+    const DROP_REPEATABLE_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN repeatable";
+    const SELECT_NEXT_BATCH: &'static str =
+        "SELECT CAST(COALESCE(max(batch), 0) + 1 AS SIGNED) FROM {table}";
+    const SELECT_LAST_BATCH: &'static str =
+        "SELECT name FROM {table} WHERE `schema` = ? AND batch = (SELECT max(batch) FROM {table} WHERE `schema` = ?)";
+    const SELECT_CHECKSUM: &'static str =
+        "SELECT checksum FROM {table} WHERE name = ? AND `schema` = ?";
+    const UPDATE_CHECKSUM: &'static str =
+        "UPDATE {table} SET checksum = ? WHERE name = ? AND `schema` = ?";
+    const RENAME_MIGRATION: &'static str =
+        "UPDATE {table} SET name = ? WHERE name = ? AND `schema` = ?";
+    const ACQUIRE_LOCK: Option<&'static str> =
+        Some("SELECT GET_LOCK(CONCAT('sqlx-simple-migrator-', ?), -1)");
+    const TRY_ACQUIRE_LOCK: Option<&'static str> =
+        Some("SELECT GET_LOCK(CONCAT('sqlx-simple-migrator-', ?), 0)");
+    const RELEASE_LOCK: Option<&'static str> =
+        Some("SELECT RELEASE_LOCK(CONCAT('sqlx-simple-migrator-', ?))");
+    // MySQL has no transaction-scoped settings, so these apply to the rest of
+    // the session. `max_execution_time` only limits `SELECT` statements.
+    const SET_STATEMENT_TIMEOUT: Option<&'static str> =
+        Some("SET SESSION max_execution_time = {milliseconds}");
+    const SET_LOCK_TIMEOUT: Option<&'static str> =
+        Some("SET SESSION innodb_lock_wait_timeout = {seconds}");
+    // Deadlocks and serialization failures. Lock wait timeouts share the
+    // generic HY000 state, so they aren't retried.
+    const TRANSIENT_ERROR_CODES: &'static [&'static str] = &["40001"];
+    const UNDEFINED_TABLE_ERROR_CODES: &'static [&'static str] = &["42S02"];
+    const SELECT_SQLX_MIGRATIONS: &'static str = r#"
+        SELECT CAST(version AS CHAR) AS version, description, CAST(NULL AS CHAR) AS script
+        FROM _sqlx_migrations
+        WHERE success ORDER BY installed_on
+        "#;
+    const SELECT_FLYWAY_MIGRATIONS: &'static str = r#"
+        SELECT version, description, script FROM flyway_schema_history
+        WHERE success AND version IS NOT NULL AND type <> 'DELETE'
+        ORDER BY installed_rank
+        "#;
+    const SELECT_DIESEL_MIGRATIONS: &'static str = r#"
+        SELECT version, CAST(NULL AS CHAR) AS description, CAST(NULL AS CHAR) AS script
+        FROM __diesel_schema_migrations ORDER BY run_on, version
+        "#;
+    const SELECT_BACKFILL_BOUND: &'static str = r#"
+        SELECT CAST(max({key}) AS SIGNED) FROM (
+            SELECT {key} FROM {table} WHERE {key} > {after} AND ({filter}) ORDER BY {key} LIMIT {limit}
+        ) batch
+        "#;
+    const CREATE_CHECKPOINTS_TABLE: &'static str = r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            name VARCHAR(255) NOT NULL PRIMARY KEY,
+            position TEXT NOT NULL,
+            updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+        )
+        "#;
+    const SELECT_CHECKPOINT: &'static str = "SELECT position FROM {table} WHERE name = ?";
+    const SAVE_CHECKPOINT: &'static str =
+        "INSERT INTO {table} (name, position) VALUES (?, ?) ON DUPLICATE KEY UPDATE position = VALUES(position)";
+    const DELETE_CHECKPOINT: &'static str = "DELETE FROM {table} WHERE name = ?";
+    const ANALYZE_TABLE: &'static str = "ANALYZE TABLE {table}";
+    const SELECT_TABLE: &'static str = r#"
+        SELECT table_name FROM information_schema.tables
+        WHERE table_name = ? AND table_schema = DATABASE()
+        "#;
+    const SELECT_COLUMN_TYPE: &'static str = r#"
+        SELECT data_type FROM information_schema.columns
+        WHERE table_name = ? AND column_name = ? AND table_schema = DATABASE()
+        "#;
+
+    backend_queries!();
+}
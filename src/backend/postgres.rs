@@ -0,0 +1,152 @@
+use super::MigrationBackend;
+use sqlx::Postgres;
+
+impl MigrationBackend for Postgres {
+    // Concurrent `CREATE TABLE IF NOT EXISTS` statements can still conflict,
+    // so creating the table is serialized by a lock held until the
+    // transaction ends
+    const CREATE_MIGRATIONS_TABLE: &'static str = r#"
+        SELECT pg_advisory_xact_lock(hashtext('{table}'));
+        CREATE TABLE IF NOT EXISTS {table} (
+            name TEXT NOT NULL PRIMARY KEY,
+            executed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )
+        "#;
+    const DROP_MIGRATIONS_TABLE: &'static str = r#"
+        DROP TABLE IF EXISTS {table}
+        "#;
+    const SELECT_MIGRATIONS: &'static str = "SELECT * FROM {table}";
+    const SELECT_APPLIED_MIGRATIONS: &'static str =
+        "SELECT * FROM {table} ORDER BY executed_at, name";
+    const INSERT_MIGRATION: &'static str =
+        "INSERT INTO {table} (name) VALUES ($1) ON CONFLICT DO NOTHING";
+    const INSERT_MIGRATION_WITH_DETAILS: &'static str =
+        "INSERT INTO {table} (name, checksum, duration_ms, statement_count, applied_by, hostname, app_version, build_id, description, schema, batch, repeatable) VALUES ($1, $2, $3, $4, current_user, $5, $6, $7, $8, $9, $10, $11) ON CONFLICT DO NOTHING";
+    const DELETE_MIGRATION: &'static str = "DELETE FROM {table} WHERE name = $1";
+    const DELETE_MIGRATION_WITH_SCHEMA: &'static str =
+        "DELETE FROM {table} WHERE name = $1 AND schema = $2";
+    const ADD_CHECKSUM_COLUMN: &'static str =
+        "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS checksum TEXT";
+    const DROP_CHECKSUM_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN checksum";
+    const ADD_STATISTICS_COLUMNS: &'static str =
+        "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS duration_ms BIGINT, ADD COLUMN IF NOT EXISTS statement_count BIGINT";
+    const DROP_STATISTICS_COLUMNS: &'static str =
+        "ALTER TABLE {table} DROP COLUMN duration_ms, DROP COLUMN statement_count";
+    const ADD_APPLIED_BY_COLUMNS: &'static str =
+        "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS applied_by TEXT, ADD COLUMN IF NOT EXISTS hostname TEXT, ADD COLUMN IF NOT EXISTS app_version TEXT";
+    const DROP_APPLIED_BY_COLUMNS: &'static str =
+        "ALTER TABLE {table} DROP COLUMN applied_by, DROP COLUMN hostname, DROP COLUMN app_version";
+    const ADD_BUILD_ID_COLUMN: &'static str =
+        "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS build_id TEXT";
+    const DROP_BUILD_ID_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN build_id";
+    const ADD_DESCRIPTION_COLUMN: &'static str =
+        "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS description TEXT";
+    const DROP_DESCRIPTION_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN description";
+    // The primary key created with the table is named after it, which can't
+    // be derived from a schema-qualified name
+    const ADD_SCHEMA_COLUMN: &'static str = r#"
+        ALTER TABLE {table} ADD COLUMN IF NOT EXISTS schema TEXT NOT NULL DEFAULT '';
+        DO $$
+        DECLARE
+            pkey TEXT;
+        BEGIN
+            SELECT conname INTO pkey FROM pg_constraint
+            WHERE conrelid = '{table}'::regclass AND contype = 'p' AND cardinality(conkey) = 1;
+            IF pkey IS NOT NULL THEN
+                EXECUTE format('ALTER TABLE {table} DROP CONSTRAINT %I, ADD PRIMARY KEY (schema, name)', pkey);
+            END IF;
+        END $$
+        "#;
+    // Dropping the column drops the primary key including it
+    const DROP_SCHEMA_COLUMN: &'static str = r#"
+        DELETE FROM {table} WHERE schema <> '';
+        ALTER TABLE {table} DROP COLUMN schema, ADD PRIMARY KEY (name)
+        "#;
+    const ADD_BATCH_COLUMN: &'static str =
+        "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS batch BIGINT";
+    const DROP_BATCH_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN batch";
+    const ADD_REPEATABLE_COLUMN: &'static str =
+        "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS repeatable BOOLEAN NOT NULL DEFAULT FALSE";
+    const DROP_REPEATABLE_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN repeatable";
+    const SELECT_NEXT_BATCH: &'static str = "SELECT COALESCE(max(batch), 0) + 1 FROM {table}";
+    const SELECT_LAST_BATCH: &'static str =
+        "SELECT name FROM {table} WHERE schema = $1 AND batch = (SELECT max(batch) FROM {table} WHERE schema = $2)";
+    const SELECT_CHECKSUM: &'static str =
+        "SELECT checksum FROM {table} WHERE name = $1 AND schema = $2";
+    const UPDATE_CHECKSUM: &'static str =
+        "UPDATE {table} SET checksum = $1 WHERE name = $2 AND schema = $3";
+    const RENAME_MIGRATION: &'static str =
+        "UPDATE {table} SET name = $1 WHERE name = $2 AND schema = $3";
+    const ACQUIRE_LOCK: Option<&'static str> = Some("SELECT pg_advisory_lock($1)");
+    const TRY_ACQUIRE_LOCK: Option<&'static str> =
+        Some("SELECT pg_try_advisory_lock($1)::INT::BIGINT");
+    const RELEASE_LOCK: Option<&'static str> = Some("SELECT pg_advisory_unlock($1)");
+    const SET_STATEMENT_TIMEOUT: Option<&'static str> =
+        Some("SET LOCAL statement_timeout = {milliseconds}");
+    const SET_LOCK_TIMEOUT: Option<&'static str> = Some("SET LOCAL lock_timeout = {milliseconds}");
+    const SET_ISOLATION_LEVEL: Option<&'static str> =
+        Some("SET TRANSACTION ISOLATION LEVEL {level}");
+    // serialization_failure, deadlock_detected and lock_not_available
+    const TRANSIENT_ERROR_CODES: &'static [&'static str] = &["40001", "40P01", "55P03"];
+    const UNDEFINED_TABLE_ERROR_CODES: &'static [&'static str] = &["42P01", "3F000"];
+    const SET_SEARCH_PATH: Option<&'static str> = Some("SET search_path TO {schema}");
+    const RESET_SEARCH_PATH: Option<&'static str> = Some("RESET search_path");
+    const SET_LOCAL_SEARCH_PATH: Option<&'static str> = Some("SET LOCAL search_path TO {schema}");
+    const CREATE_SCHEMA: Option<&'static str> = Some("CREATE SCHEMA {schema}");
+    const DROP_SCHEMA: Option<&'static str> = Some("DROP SCHEMA {schema} CASCADE");
+    const SELECT_CURRENT_SCHEMA: Option<&'static str> = Some("SELECT current_schema()::TEXT");
+    const SELECT_AVAILABLE_EXTENSION: Option<&'static str> =
+        Some("SELECT name::TEXT FROM pg_available_extensions WHERE name = $1");
+    // Views, materialized views and foreign tables are granted `ON TABLE`
+    const SELECT_OBJECTS: Option<&'static str> = Some(
+        r#"
+        SELECT format('%I.%I', n.nspname, c.relname) AS name,
+            CASE WHEN c.relkind = 'S' THEN 'SEQUENCE' ELSE 'TABLE' END AS kind
+        FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f', 'S')
+            AND n.nspname = ANY (current_schemas(false))
+        "#,
+    );
+    const NOTIFY: Option<&'static str> = Some("SELECT pg_notify($1, $2)");
+    const SELECT_SQLX_MIGRATIONS: &'static str = r#"
+        SELECT version::TEXT AS version, description, NULL::TEXT AS script
+        FROM _sqlx_migrations
+        WHERE success ORDER BY installed_on
+        "#;
+    const SELECT_FLYWAY_MIGRATIONS: &'static str = r#"
+        SELECT version::TEXT AS version, description, script FROM flyway_schema_history
+        WHERE success AND version IS NOT NULL AND type <> 'DELETE'
+        ORDER BY installed_rank
+        "#;
+    const SELECT_DIESEL_MIGRATIONS: &'static str = r#"
+        SELECT version, NULL::TEXT AS description, NULL::TEXT AS script
+        FROM __diesel_schema_migrations ORDER BY run_on, version
+        "#;
+    const SELECT_BACKFILL_BOUND: &'static str = r#"
+        SELECT max({key})::BIGINT FROM (
+            SELECT {key} FROM {table} WHERE {key} > {after} AND ({filter}) ORDER BY {key} LIMIT {limit}
+        ) batch
+        "#;
+    const CREATE_CHECKPOINTS_TABLE: &'static str = r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            name TEXT NOT NULL PRIMARY KEY,
+            position TEXT NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )
+        "#;
+    const SELECT_CHECKPOINT: &'static str = "SELECT position FROM {table} WHERE name = $1";
+    const SAVE_CHECKPOINT: &'static str =
+        "INSERT INTO {table} (name, position) VALUES ($1, $2) ON CONFLICT (name) DO UPDATE SET position = excluded.position, updated_at = now()";
+    const DELETE_CHECKPOINT: &'static str = "DELETE FROM {table} WHERE name = $1";
+    const ANALYZE_TABLE: &'static str = "ANALYZE {table}";
+    const SELECT_TABLE: &'static str = r#"
+        SELECT table_name::TEXT FROM information_schema.tables
+        WHERE table_name = $1 AND table_schema = ANY (current_schemas(false))
+        "#;
+    const SELECT_COLUMN_TYPE: &'static str = r#"
+        SELECT data_type::TEXT FROM information_schema.columns
+        WHERE table_name = $1 AND column_name = $2 AND table_schema = ANY (current_schemas(false))
+        "#;
+
+    backend_queries!();
+}
@@ -0,0 +1,157 @@
+use super::MigrationBackend;
+use sqlx::Sqlite;
+
+/// SQLite only supports a handful of `ALTER TABLE` operations, so most schema
+/// changes require the "create new table, copy, drop, rename" procedure. Foreign
+/// key enforcement is disabled while each migration runs so that dropping and
+/// renaming referenced tables doesn't cascade or fail. SQLite ignores this
+/// pragma inside of a transaction, which is why it is issued before `BEGIN`.
+/// Migrations that rebuild tables should verify their work with
+/// `PRAGMA foreign_key_check`.
+impl MigrationBackend for Sqlite {
+    const CREATE_MIGRATIONS_TABLE: &'static str = r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            name TEXT NOT NULL PRIMARY KEY,
+            executed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#;
+    const DROP_MIGRATIONS_TABLE: &'static str = r#"
+        DROP TABLE IF EXISTS {table}
+        "#;
+    const SELECT_MIGRATIONS: &'static str = "SELECT * FROM {table}";
+    const SELECT_APPLIED_MIGRATIONS: &'static str =
+        "SELECT * FROM {table} ORDER BY executed_at, name";
+    const INSERT_MIGRATION: &'static str =
+        "INSERT INTO {table} (name) VALUES (?) ON CONFLICT DO NOTHING";
+    const INSERT_MIGRATION_WITH_DETAILS: &'static str =
+        "INSERT INTO {table} (name, checksum, duration_ms, statement_count, hostname, app_version, build_id, description, schema, batch, repeatable) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT DO NOTHING";
+    const DELETE_MIGRATION: &'static str = "DELETE FROM {table} WHERE name = ?";
+    const DELETE_MIGRATION_WITH_SCHEMA: &'static str =
+        "DELETE FROM {table} WHERE name = ? AND schema = ?";
+    const BEFORE_MIGRATION: &'static [&'static str] = &["PRAGMA foreign_keys = OFF"];
+    const AFTER_MIGRATION: &'static [&'static str] = &["PRAGMA foreign_keys = ON"];
+    // SQLITE_BUSY, SQLITE_LOCKED and their extended codes
+    const TRANSIENT_ERROR_CODES: &'static [&'static str] = &["5", "6", "261", "262", "517"];
+    // SQLite reports a missing table as the generic SQLITE_ERROR
+    const UNDEFINED_TABLE_ERROR_CODES: &'static [&'static str] = &["1"];
+    const ADD_CHECKSUM_COLUMN: &'static str = "ALTER TABLE {table} ADD COLUMN checksum TEXT";
+    const DROP_CHECKSUM_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN checksum";
+    const ADD_STATISTICS_COLUMNS: &'static str = r#"
+        ALTER TABLE {table} ADD COLUMN duration_ms INTEGER;
+        ALTER TABLE {table} ADD COLUMN statement_count INTEGER
+        "#;
+    const DROP_STATISTICS_COLUMNS: &'static str = r#"
+        ALTER TABLE {table} DROP COLUMN duration_ms;
+        ALTER TABLE {table} DROP COLUMN statement_count
+        "#;
+    // SQLite has no users, so `applied_by` is left empty
+    const ADD_APPLIED_BY_COLUMNS: &'static str = r#"
+        ALTER TABLE {table} ADD COLUMN applied_by TEXT;
+        ALTER TABLE {table} ADD COLUMN hostname TEXT;
+        ALTER TABLE {table} ADD COLUMN app_version TEXT
+        "#;
+    const DROP_APPLIED_BY_COLUMNS: &'static str = r#"
+        ALTER TABLE {table} DROP COLUMN applied_by;
+        ALTER TABLE {table} DROP COLUMN hostname;
+        ALTER TABLE {table} DROP COLUMN app_version
+        "#;
+    const ADD_BUILD_ID_COLUMN: &'static str = "ALTER TABLE {table} ADD COLUMN build_id TEXT";
+    const DROP_BUILD_ID_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN build_id";
+    const ADD_DESCRIPTION_COLUMN: &'static str = "ALTER TABLE {table} ADD COLUMN description TEXT";
+    const DROP_DESCRIPTION_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN description";
+    // The primary key can't be altered, so the table is rebuilt. It's copied
+    // through a temporary table rather than renamed, as `RENAME TO` doesn't
+    // accept a schema-qualified name.
+    const ADD_SCHEMA_COLUMN: &'static str = r#"
+        CREATE TEMP TABLE sqlx_simple_migrator_rebuild AS SELECT * FROM {table};
+        DROP TABLE {table};
+        CREATE TABLE {table} (
+            schema TEXT NOT NULL DEFAULT '',
+            name TEXT NOT NULL,
+            executed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            checksum TEXT,
+            duration_ms INTEGER,
+            statement_count INTEGER,
+            applied_by TEXT,
+            hostname TEXT,
+            app_version TEXT,
+            build_id TEXT,
+            description TEXT,
+            PRIMARY KEY (schema, name)
+        );
+        INSERT INTO {table} (name, executed_at, checksum, duration_ms, statement_count, applied_by, hostname, app_version, build_id, description)
+        SELECT name, executed_at, checksum, duration_ms, statement_count, applied_by, hostname, app_version, build_id, description
+        FROM temp.sqlx_simple_migrator_rebuild;
+        DROP TABLE temp.sqlx_simple_migrator_rebuild
+        "#;
+    const DROP_SCHEMA_COLUMN: &'static str = r#"
+        CREATE TEMP TABLE sqlx_simple_migrator_rebuild AS SELECT * FROM {table} WHERE schema = '';
+        DROP TABLE {table};
+        CREATE TABLE {table} (
+            name TEXT NOT NULL PRIMARY KEY,
+            executed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            checksum TEXT,
+            duration_ms INTEGER,
+            statement_count INTEGER,
+            applied_by TEXT,
+            hostname TEXT,
+            app_version TEXT,
+            build_id TEXT,
+            description TEXT
+        );
+        INSERT INTO {table} (name, executed_at, checksum, duration_ms, statement_count, applied_by, hostname, app_version, build_id, description)
+        SELECT name, executed_at, checksum, duration_ms, statement_count, applied_by, hostname, app_version, build_id, description
+        FROM temp.sqlx_simple_migrator_rebuild;
+        DROP TABLE temp.sqlx_simple_migrator_rebuild
+        "#;
+    const ADD_BATCH_COLUMN: &'static str = "ALTER TABLE {table} ADD COLUMN batch INTEGER";
+    const DROP_BATCH_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN batch";
+    const ADD_REPEATABLE_COLUMN: &'static str =
+        "ALTER TABLE {table} ADD COLUMN repeatable INTEGER NOT NULL DEFAULT 0";
+    const DROP_REPEATABLE_COLUMN: &'static str = "ALTER TABLE {table} DROP COLUMN repeatable";
+    const SELECT_NEXT_BATCH: &'static str = "SELECT COALESCE(max(batch), 0) + 1 FROM {table}";
+    const SELECT_LAST_BATCH: &'static str =
+        "SELECT name FROM {table} WHERE schema = ? AND batch = (SELECT max(batch) FROM {table} WHERE schema = ?)";
+    const SELECT_CHECKSUM: &'static str =
+        "SELECT checksum FROM {table} WHERE name = ? AND schema = ?";
+    const UPDATE_CHECKSUM: &'static str =
+        "UPDATE {table} SET checksum = ? WHERE name = ? AND schema = ?";
+    const RENAME_MIGRATION: &'static str =
+        "UPDATE {table} SET name = ? WHERE name = ? AND schema = ?";
+    const SELECT_SQLX_MIGRATIONS: &'static str = r#"
+        SELECT CAST(version AS TEXT) AS version, description, NULL AS script
+        FROM _sqlx_migrations
+        WHERE success ORDER BY installed_on
+        "#;
+    const SELECT_FLYWAY_MIGRATIONS: &'static str = r#"
+        SELECT version, description, script FROM flyway_schema_history
+        WHERE success AND version IS NOT NULL AND type <> 'DELETE'
+        ORDER BY installed_rank
+        "#;
+    const SELECT_DIESEL_MIGRATIONS: &'static str = r#"
+        SELECT version, NULL AS description, NULL AS script
+        FROM __diesel_schema_migrations ORDER BY run_on, version
+        "#;
+    const SELECT_BACKFILL_BOUND: &'static str = r#"
+        SELECT max({key}) FROM (
+            SELECT {key} FROM {table} WHERE {key} > {after} AND ({filter}) ORDER BY {key} LIMIT {limit}
+        ) batch
+        "#;
+    const CREATE_CHECKPOINTS_TABLE: &'static str = r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            name TEXT NOT NULL PRIMARY KEY,
+            position TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#;
+    const SELECT_CHECKPOINT: &'static str = "SELECT position FROM {table} WHERE name = ?";
+    const SAVE_CHECKPOINT: &'static str =
+        "INSERT INTO {table} (name, position) VALUES (?, ?) ON CONFLICT (name) DO UPDATE SET position = excluded.position, updated_at = CURRENT_TIMESTAMP";
+    const DELETE_CHECKPOINT: &'static str = "DELETE FROM {table} WHERE name = ?";
+    const ANALYZE_TABLE: &'static str = "ANALYZE {table}";
+    const SELECT_TABLE: &'static str =
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?";
+    const SELECT_COLUMN_TYPE: &'static str = "SELECT type FROM pragma_table_info(?) WHERE name = ?";
+
+    backend_queries!();
+}
@@ -0,0 +1,136 @@
+use super::{logging, Checkpoint, MigrationBackend};
+use sqlx::Connection;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+/// Updates a large table in batches, each in its own transaction, so that a
+/// data migration doesn't hold locks on the whole table or produce one huge
+/// transaction. Batches are selected by keyset pagination on an integer key.
+pub struct Backfill {
+    pub table: String,
+    /// The assignments of the `UPDATE`, such as `full_name = first || last`
+    pub set: String,
+    /// The integer column batches are paginated by. Defaults to `id`.
+    pub key: String,
+    /// A condition limiting which rows are updated
+    pub filter: Option<String>,
+    /// The number of rows updated by each batch. Defaults to 1000.
+    pub batch_size: u64,
+    /// How long to wait between batches, to leave room for other traffic
+    pub pause: Option<Duration>,
+    /// Where the last key updated is saved, so that an interrupted backfill
+    /// resumes after it
+    pub checkpoint: Option<Checkpoint>,
+}
+
+impl Backfill {
+    /// Create a backfill that executes `UPDATE table SET set` in batches
+    pub fn new(table: &str, set: &str) -> Self {
+        Backfill {
+            table: table.to_owned(),
+            set: set.to_owned(),
+            key: String::from("id"),
+            filter: None,
+            batch_size: 1000,
+            pause: None,
+            checkpoint: None,
+        }
+    }
+
+    /// Set the integer column batches are paginated by
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = key.to_owned();
+        self
+    }
+
+    /// Only update the rows matching `filter`, such as `full_name IS NULL`
+    pub fn filter(mut self, filter: &str) -> Self {
+        self.filter = Some(filter.to_owned());
+        self
+    }
+
+    /// Set the number of rows updated by each batch
+    pub fn batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Wait for `pause` between batches
+    pub fn pause(mut self, pause: Duration) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    /// Save the last key updated by each batch under the checkpoint `name`,
+    /// within the batch's transaction, so that a backfill interrupted by a
+    /// crash resumes after the last batch committed. The checkpoint is
+    /// cleared once the backfill completes.
+    pub fn checkpoint(mut self, name: &str) -> Self {
+        self.checkpoint = Some(Checkpoint::new(name));
+        self
+    }
+
+    /// Executes the backfill on `conn`, committing each batch before
+    /// starting the next. When `conn` is already within a transaction, each
+    /// batch is executed within a savepoint instead.
+    pub async fn run<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+    ) -> Result<(), sqlx::Error> {
+        let filter = self.filter.as_deref().unwrap_or("1 = 1");
+        let render = |statement: &str, after: i64| {
+            statement
+                .replace("{table}", &self.table)
+                .replace("{key}", &self.key)
+                .replace("{filter}", filter)
+                .replace("{after}", &after.to_string())
+                .replace("{limit}", &self.batch_size.to_string())
+        };
+        let mut after = match &self.checkpoint {
+            Some(checkpoint) => match checkpoint.load::<DB>(conn).await? {
+                Some(saved) => {
+                    let saved = saved.parse().map_err(|_| {
+                        sqlx::Error::Protocol(format!(
+                            "checkpoint {} isn't a key: {:?}",
+                            checkpoint.name, saved
+                        ))
+                    })?;
+                    logging::backfill_resumed(&self.table, saved);
+                    saved
+                }
+                None => i64::MIN,
+            },
+            None => i64::MIN,
+        };
+        let mut batches = 0;
+        loop {
+            let mut tx = conn.begin().await?;
+            let last =
+                match DB::query_bound(&mut tx, &render(DB::SELECT_BACKFILL_BOUND, after)).await? {
+                    Some(last) => last,
+                    None => {
+                        if let Some(checkpoint) = &self.checkpoint {
+                            checkpoint.clear::<DB>(&mut tx).await?;
+                        }
+                        return tx.commit().await;
+                    }
+                };
+            let update = format!(
+                "UPDATE {} SET {} WHERE {} > {} AND {} <= {} AND ({})",
+                self.table, self.set, self.key, after, self.key, last, filter
+            );
+            DB::execute(&mut tx, &update).await?;
+            if let Some(checkpoint) = &self.checkpoint {
+                checkpoint.save::<DB>(&mut tx, &last.to_string()).await?;
+            }
+            tx.commit().await?;
+
+            batches += 1;
+            logging::backfill_progress(&self.table, batches, last);
+            after = last;
+            if let Some(pause) = self.pause {
+                tokio::time::sleep(pause).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use super::Migration;
+
+impl Migration {
+    /// Add each statement in `sql`, a script of many statements such as the
+    /// output of `pg_dump`, as an "Up" statement. Statements are split on `;`
+    /// outside of string literals, quoted identifiers, dollar-quoted bodies and
+    /// comments. Comments before each statement and empty statements are
+    /// dropped.
+    pub fn with_up_batch(self, sql: &str) -> Self {
+        split(sql)
+            .into_iter()
+            .fold(self, |migration, statement| migration.with_up(statement))
+    }
+}
+
+/// Splits `sql` into its statements, without their terminating `;`
+pub(crate) fn split(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let end = match bytes[i] {
+            b';' => {
+                if let Some(start) = start.take() {
+                    statements.push(sql[start..i].trim_end());
+                }
+                i += 1;
+                continue;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                let end = line_comment_end(bytes, i);
+                if start.is_none() {
+                    i = end;
+                    continue;
+                }
+                end
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = block_comment_end(bytes, i);
+                if start.is_none() {
+                    i = end;
+                    continue;
+                }
+                end
+            }
+            byte if byte.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'\'' => quoted_end(bytes, i, b'\'', is_escape_string(bytes, i)),
+            quote @ (b'"' | b'`') => quoted_end(bytes, i, quote, false),
+            b'$' => dollar_quoted_end(sql, i).unwrap_or(i + 1),
+            _ => i + 1,
+        };
+        start.get_or_insert(i);
+        i = end;
+    }
+    if let Some(start) = start {
+        statements.push(sql[start..].trim_end());
+    }
+    statements
+}
+
+fn is_identifier(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// Returns true if the string literal starting at `i` is written as `E'...'`,
+/// in which backslashes escape the following character
+fn is_escape_string(bytes: &[u8], i: usize) -> bool {
+    i > 0 && bytes[i - 1].eq_ignore_ascii_case(&b'e') && (i < 2 || !is_identifier(bytes[i - 2]))
+}
+
+fn line_comment_end(bytes: &[u8], i: usize) -> usize {
+    bytes[i..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(bytes.len(), |position| i + position + 1)
+}
+
+/// Returns the end of the block comment starting at `i`. Block comments nest.
+fn block_comment_end(bytes: &[u8], i: usize) -> usize {
+    let mut depth = 0;
+    let mut j = i;
+    while j + 1 < bytes.len() {
+        match (bytes[j], bytes[j + 1]) {
+            (b'/', b'*') => {
+                depth += 1;
+                j += 2;
+            }
+            (b'*', b'/') => {
+                depth -= 1;
+                j += 2;
+                if depth == 0 {
+                    return j;
+                }
+            }
+            _ => j += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Returns the end of the string literal or quoted identifier starting at `i`.
+/// A doubled `quote` is part of the literal.
+fn quoted_end(bytes: &[u8], i: usize, quote: u8, backslash_escapes: bool) -> usize {
+    let mut j = i + 1;
+    while j < bytes.len() {
+        if backslash_escapes && bytes[j] == b'\\' {
+            j += 2;
+        } else if bytes[j] == quote {
+            if bytes.get(j + 1) != Some(&quote) {
+                return j + 1;
+            }
+            j += 2;
+        } else {
+            j += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Returns the end of the dollar-quoted body starting at `i`, such as
+/// `$$ ... $$` or `$body$ ... $body$`, or `None` if the `$` at `i` doesn't
+/// start one
+fn dollar_quoted_end(sql: &str, i: usize) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    if i > 0 && (is_identifier(bytes[i - 1]) || bytes[i - 1] == b'$') {
+        return None;
+    }
+    let tag_length = bytes[i + 1..]
+        .iter()
+        .take_while(|&&byte| is_identifier(byte))
+        .count();
+    let close = i + 1 + tag_length;
+    if bytes.get(close) != Some(&b'$') || bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+        return None;
+    }
+    let tag = &sql[i..=close];
+    Some(
+        sql[close + 1..]
+            .find(tag)
+            .map_or(sql.len(), |position| close + 1 + position + tag.len()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_semicolons() {
+        assert_eq!(
+            split("CREATE TABLE a (id INT);\nCREATE TABLE b (id INT);"),
+            vec!["CREATE TABLE a (id INT)", "CREATE TABLE b (id INT)"]
+        );
+    }
+
+    #[test]
+    fn keeps_a_final_statement_without_a_semicolon() {
+        assert_eq!(
+            split("DROP TABLE a; DROP TABLE b"),
+            vec!["DROP TABLE a", "DROP TABLE b"]
+        );
+    }
+
+    #[test]
+    fn drops_empty_statements() {
+        assert_eq!(split(";;  SELECT 1;\n;\n"), vec!["SELECT 1"]);
+        assert!(split("  \n").is_empty());
+    }
+
+    #[test]
+    fn respects_string_literals() {
+        assert_eq!(
+            split("INSERT INTO a VALUES ('x;y', 'it''s;'); SELECT 1"),
+            vec!["INSERT INTO a VALUES ('x;y', 'it''s;')", "SELECT 1"]
+        );
+        assert_eq!(
+            split(r"SELECT E'\';'; SELECT 'C:\'; SELECT 1"),
+            vec![r"SELECT E'\';'", r"SELECT 'C:\'", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn respects_quoted_identifiers() {
+        assert_eq!(
+            split(r#"CREATE TABLE "a;b" (id INT); CREATE TABLE `c;d` (id INT)"#),
+            vec![
+                r#"CREATE TABLE "a;b" (id INT)"#,
+                "CREATE TABLE `c;d` (id INT)"
+            ]
+        );
+    }
+
+    #[test]
+    fn respects_dollar_quoted_bodies() {
+        let function =
+            "CREATE FUNCTION f() RETURNS INT AS $$ BEGIN RETURN 1; END; $$ LANGUAGE plpgsql";
+        assert_eq!(
+            split(&format!("{};SELECT f();", function)),
+            vec![function, "SELECT f()"]
+        );
+
+        let tagged = "CREATE FUNCTION g() RETURNS TEXT AS $body$ SELECT $$;$$; $body$ LANGUAGE sql";
+        assert_eq!(split(&format!("{};", tagged)), vec![tagged]);
+    }
+
+    #[test]
+    fn ignores_parameters_and_dollars_in_identifiers() {
+        assert_eq!(
+            split("SELECT $1, a$b$ FROM t; SELECT 2"),
+            vec!["SELECT $1, a$b$ FROM t", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn respects_comments() {
+        assert_eq!(
+            split(
+                "-- drop; the 'table\nDROP TABLE a; /* one; /* two; */ it's */ SELECT 1 -- done;\n"
+            ),
+            vec!["DROP TABLE a", "SELECT 1 -- done;"]
+        );
+    }
+
+    #[test]
+    fn splits_pg_dump_output() {
+        let dump = r#"
+--
+-- PostgreSQL database dump
+--
+
+SET statement_timeout = 0;
+SELECT pg_catalog.set_config('search_path', '', false);
+
+--
+-- Name: touch(); Type: FUNCTION; Schema: public; Owner: postgres
+--
+
+CREATE FUNCTION public.touch() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+BEGIN
+    NEW.updated_at := now();
+    RETURN NEW;
+END;
+$$;
+
+--
+-- Name: users; Type: TABLE; Schema: public; Owner: postgres
+--
+
+CREATE TABLE public.users (
+    id bigint NOT NULL,
+    name text DEFAULT 'anonymous;'::text
+);
+"#;
+        let statements = split(dump);
+        assert_eq!(statements.len(), 4);
+        assert_eq!(statements[0], "SET statement_timeout = 0");
+        assert!(statements[2].starts_with("CREATE FUNCTION public.touch()"));
+        assert!(statements[2].ends_with("END;\n$$"));
+        assert!(statements[3].starts_with("CREATE TABLE public.users"));
+    }
+
+    #[test]
+    fn with_up_batch_adds_each_statement() {
+        let migration = Migration::new("batch").with_up_batch("SELECT 1; SELECT 2;");
+        assert_eq!(migration.up, vec!["SELECT 1", "SELECT 2"]);
+    }
+}
@@ -0,0 +1,117 @@
+use clap::{Parser, Subcommand};
+use sqlx_simple_migrator::{
+    scaffold::{self, Style},
+    MigrationDirectory, MigratorConfig, PgMigrator, Policy,
+};
+use std::{error::Error, path::Path, path::PathBuf};
+
+#[derive(Parser)]
+#[clap(
+    name = "simple-migrator",
+    version,
+    about = "Executes migrations from a directory of sql files"
+)]
+struct Cli {
+    /// The PostgreSQL database to migrate
+    #[clap(long, env = "DATABASE_URL")]
+    database_url: Option<String>,
+    /// The directory containing the migration files
+    #[clap(long, default_value = "migrations")]
+    source: PathBuf,
+    /// The name of the table that records which migrations have been performed
+    #[clap(long)]
+    table_name: Option<String>,
+    /// The schema containing the migrations table
+    #[clap(long)]
+    schema: Option<String>,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Perform all pending migrations
+    Up,
+    /// Undo the most recently applied migrations
+    Down {
+        /// The number of migrations to undo
+        #[clap(long, default_value = "1")]
+        steps: usize,
+    },
+    /// List the applied, pending, unknown and drifted migrations
+    Status {
+        /// Print the status as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Check that the migration files can be loaded and are consistent with
+    /// the database, without executing anything
+    Validate,
+    /// Create empty up and down files for a new migration
+    New {
+        /// The name of the migration, without its number
+        name: String,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let runtime = tokio::runtime::Runtime::new().expect("error starting runtime");
+    if let Err(err) = runtime.block_on(execute(cli)) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn execute(cli: Cli) -> Result<(), Box<dyn Error>> {
+    if let Command::New { name } = &cli.command {
+        return new_migration(&cli.source, name);
+    }
+
+    let database_url = cli
+        .database_url
+        .as_deref()
+        .ok_or("a database url is required, using --database-url or DATABASE_URL")?;
+    let mut config = MigratorConfig::default();
+    if let Some(table_name) = &cli.table_name {
+        config = config.table_name(table_name);
+    }
+    if let Some(schema) = &cli.schema {
+        config = config.schema(schema);
+    }
+    let migrator = PgMigrator::connect(database_url)
+        .await?
+        .with_migrations(MigrationDirectory::from_dir(&cli.source).migrations()?)
+        .with_config(config);
+
+    match cli.command {
+        Command::Up => {
+            for applied in migrator.run().await?.applied {
+                println!("applied  {} ({:?})", applied.name, applied.duration);
+            }
+        }
+        Command::Down { steps } => migrator.rollback(steps).await?,
+        Command::Status { json } => {
+            let status = migrator.status().await?;
+            if json {
+                println!("{}", status.to_json());
+            } else {
+                println!("{}", status);
+            }
+        }
+        Command::Validate => {
+            let planned = migrator.unknown_applied(Policy::Error).dry_run().await?;
+            println!("{} migrations to execute", planned.len());
+        }
+        Command::New { .. } => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Creates `YYYYMMDDHHMMSS_name.up.sql` and `YYYYMMDDHHMMSS_name.down.sql`
+fn new_migration(source: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+    for path in scaffold::new_migration_in(source, name, Style::Sql)? {
+        println!("created {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! Synchronous wrappers for build scripts, tests and tools that aren't
+//! async. Each call runs on a runtime of its own, so it can't be called from
+//! within an async runtime.
+
+use super::{Migration, MigrationBackend, MigrationError, MigrationReport, Migrator};
+use sqlx::Acquire;
+use std::future::Future;
+
+/// Execute all of the migrations against the database at `url`, like
+/// `Migration::run_all`. The database is chosen by the url's scheme, such as
+/// `postgres://`, among the databases enabled by the crate's features.
+pub fn run_all(url: &str, migrations: Vec<Migration>) -> Result<MigrationReport, MigrationError> {
+    block_on(async move {
+        match url.split(':').next() {
+            #[cfg(feature = "postgres")]
+            Some("postgres") | Some("postgresql") => {
+                super::PgMigrator::connect(url)
+                    .await?
+                    .with_migrations(migrations)
+                    .run()
+                    .await
+            }
+            #[cfg(feature = "mysql")]
+            Some("mysql") => {
+                super::MySqlMigrator::connect(url)
+                    .await?
+                    .with_migrations(migrations)
+                    .run()
+                    .await
+            }
+            #[cfg(feature = "sqlite")]
+            Some("sqlite") => {
+                super::SqliteMigrator::connect(url)
+                    .await?
+                    .with_migrations(migrations)
+                    .run()
+                    .await
+            }
+            _ => Err(MigrationError::Connection {
+                source: sqlx::Error::Configuration(
+                    format!("no enabled database supports the url \"{}\"", url).into(),
+                ),
+            }),
+        }
+    })
+}
+
+/// Execute all of the pending migrations of a configured `migrator`, such as
+/// one using an existing pool
+pub fn run<'a, A>(migrator: Migrator<A>) -> Result<MigrationReport, MigrationError>
+where
+    A: Acquire<'a>,
+    A::Database: MigrationBackend,
+{
+    block_on(migrator.run())
+}
+
+fn block_on<F, T>(future: F) -> Result<T, MigrationError>
+where
+    F: Future<Output = Result<T, MigrationError>>,
+{
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| MigrationError::Connection {
+            source: sqlx::Error::Io(err),
+        })?;
+    runtime.block_on(future)
+}
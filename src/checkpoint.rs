@@ -0,0 +1,78 @@
+use super::MigrationBackend;
+
+/// The progress of a long-running data migration, such as the last id it
+/// processed, kept in a side table so that a run interrupted by a crash
+/// resumes from it instead of starting over. Saving the checkpoint within
+/// the same transaction as the work it describes keeps the two in step.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    /// The table checkpoints are kept in. Defaults to
+    /// `migration_checkpoints`, and is created when first used.
+    pub table: String,
+    /// The name the checkpoint is kept under, such as the migration's name
+    pub name: String,
+}
+
+impl Checkpoint {
+    /// Create a checkpoint kept under `name`
+    pub fn new(name: &str) -> Self {
+        Checkpoint {
+            table: String::from("migration_checkpoints"),
+            name: name.to_owned(),
+        }
+    }
+
+    /// Keep the checkpoint in `table` instead of `migration_checkpoints`
+    pub fn table(mut self, table: &str) -> Self {
+        self.table = table.to_owned();
+        self
+    }
+
+    /// Returns the position last saved, creating the checkpoints table if it
+    /// doesn't exist. Returns `None` if nothing has been saved since the
+    /// checkpoint was last cleared.
+    pub async fn load<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+    ) -> Result<Option<String>, sqlx::Error> {
+        DB::execute(conn, &self.render(DB::CREATE_CHECKPOINTS_TABLE)).await?;
+        DB::query_schema(
+            conn,
+            &self.render(DB::SELECT_CHECKPOINT),
+            &[self.name.as_str()],
+        )
+        .await
+    }
+
+    /// Save `position` as the progress made so far
+    pub async fn save<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        position: &str,
+    ) -> Result<(), sqlx::Error> {
+        DB::execute_with_values(
+            conn,
+            &self.render(DB::SAVE_CHECKPOINT),
+            &[self.name.as_str(), position],
+        )
+        .await
+    }
+
+    /// Remove the saved position once the work is complete, so that running
+    /// it again starts from the beginning
+    pub async fn clear<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+    ) -> Result<(), sqlx::Error> {
+        DB::execute_with_values(
+            conn,
+            &self.render(DB::DELETE_CHECKPOINT),
+            &[self.name.as_str()],
+        )
+        .await
+    }
+
+    fn render(&self, statement: &str) -> String {
+        statement.replace("{table}", &self.table)
+    }
+}
@@ -0,0 +1,552 @@
+use super::{Environment, MigrationError, TagFilter};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
+
+#[derive(Clone, Debug)]
+/// Options controlling how migrations are executed
+pub struct MigratorConfig {
+    /// The name of the table that records which migrations have been
+    /// performed. Defaults to `migrations`.
+    pub table_name: String,
+    /// The schema containing the migrations table. When unset, the table is
+    /// resolved using the connection's default schema.
+    pub schema: Option<String>,
+    /// What to do when an applied migration's up statements have changed
+    pub checksum_mismatch: Policy,
+    /// Undo and perform again the applied migrations whose up statements have
+    /// changed, when debug modes are executed
+    pub redo_changed: bool,
+    /// Roll back and perform again the applied migrations from this migration
+    /// onward, when debug modes are executed
+    pub nuclear_debug_from: Option<String>,
+    /// What to do when a pending migration has a lower version than an
+    /// applied migration
+    pub out_of_order: Policy,
+    /// What to do when migrations have been applied that weren't supplied.
+    /// Defaults to `Policy::Ignore`.
+    pub unknown_applied: Policy,
+    /// When set, an advisory lock using this key is held while migrations
+    /// run, so that only one process executes migrations at a time
+    pub lock_key: Option<i64>,
+    /// When set, limits how long each statement of a migration may run
+    pub statement_timeout: Option<Duration>,
+    /// When set, limits how long each statement of a migration may wait to
+    /// acquire a lock
+    pub lock_timeout: Option<Duration>,
+    /// How many times to retry a migration that failed because of a
+    /// transient error. Defaults to 0.
+    pub retries: u32,
+    /// How long to wait before the first retry. The wait doubles with each
+    /// retry, up to a minute. Defaults to 100 milliseconds.
+    pub retry_backoff: Duration,
+    /// What to do with migrations in a debug mode. Defaults to
+    /// `DebugModes::Execute` with `debug_assertions` enabled, and
+    /// `DebugModes::Error` otherwise.
+    pub debug_modes: DebugModes,
+    /// When true, anything that would undo a migration returns an error
+    /// instead. Defaults to false.
+    pub forbid_down: bool,
+    /// When true, a supplied migration with up statements but no down
+    /// statements returns an error before anything is executed. Defaults to
+    /// false.
+    pub require_down: bool,
+    /// When true, undoing a migration drops the extensions it requires.
+    /// Defaults to false.
+    pub drop_extensions: bool,
+    /// Statements executed for each table, view or sequence a migration
+    /// creates, with `{object}` and `{kind}` replaced
+    pub new_object_statements: Vec<String>,
+    /// Execute `ANALYZE` on the tables each migration touched after it
+    /// commits
+    pub analyze_touched_tables: bool,
+    /// Statements executed for each table a migration touched after it
+    /// commits, with `{table}` replaced by the table's name
+    pub touched_table_statements: Vec<String>,
+    /// Values substituted for `{{name}}` placeholders in migration statements
+    pub vars: HashMap<String, String>,
+    /// What to do when a pending migration contains a statement that is
+    /// likely to cause problems in production. Defaults to `Policy::Warn`.
+    pub dangerous_statements: Policy,
+    /// The host name recorded for each migration performed. Defaults to the
+    /// `HOSTNAME` or `COMPUTERNAME` environment variable, or the contents of
+    /// `/etc/hostname`.
+    pub hostname: Option<String>,
+    /// The application version recorded for each migration performed
+    pub app_version: Option<String>,
+    /// The identifier of the build recorded for each migration performed,
+    /// such as a git commit hash
+    pub build_id: Option<String>,
+    /// Which pending migrations are performed, based on their tags. Defaults
+    /// to every migration.
+    pub tag_filter: TagFilter,
+    /// The environment selecting the migrations marked with `only_in` or
+    /// `skip_in`
+    pub environment: Option<Environment>,
+    /// When set, listeners on this channel are notified after a run applies
+    /// migrations
+    pub notify_channel: Option<String>,
+    /// When set, a run that finds the lock held waits up to this long for
+    /// the lock holder to apply the migrations, instead of waiting for the
+    /// lock
+    pub follower_timeout: Option<Duration>,
+    /// How often a waiting run checks whether the migrations have been
+    /// applied. Defaults to 1 second.
+    pub follower_poll_interval: Duration,
+    /// When true, failing to read the migrations table for any reason other
+    /// than the table not existing returns an error. When false, any failure
+    /// is treated as no migrations having been performed. Defaults to true.
+    pub strict_history: bool,
+    /// The directory relative paths given to `with_up_file` and
+    /// `with_down_file` are resolved against. Defaults to the current
+    /// directory.
+    pub migrations_root: Option<PathBuf>,
+    /// The comma-separated schemas unqualified names in migrations resolve
+    /// to. When unset, the connection's default search path is used.
+    pub search_path: Option<String>,
+    /// The schema recorded with each migration in the migrations table, so
+    /// that the histories of several schemas' migrations are tracked
+    /// separately in one table. When unset, migrations are recorded with an
+    /// empty schema.
+    pub tracked_schema: Option<String>,
+    /// Stops the run at the next statement boundary once it holds true
+    pub cancellation: Option<watch::Receiver<bool>>,
+    /// Stops the run at the next statement boundary once it passes
+    pub deadline: Option<Instant>,
+    /// The batch recorded with the migrations a run executes. Each run
+    /// assigns the next batch when it starts, so this is only set while
+    /// migrations execute.
+    pub batch: Option<i64>,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+/// How to handle a problem detected while executing migrations
+pub enum Policy {
+    /// Return an error without executing any further migrations
+    #[default]
+    Error,
+    /// Print a warning and continue
+    Warn,
+    /// Continue silently
+    Ignore,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+/// How to handle migrations marked with `debug` or `nuclear_debug`
+pub enum DebugModes {
+    /// Execute the migrations in their debug modes
+    Execute,
+    /// Print a warning and execute the migrations as if they were stable
+    Stable,
+    /// Return an error without executing any migrations
+    Error,
+}
+
+impl Default for DebugModes {
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            DebugModes::Execute
+        } else {
+            DebugModes::Error
+        }
+    }
+}
+
+impl Default for MigratorConfig {
+    fn default() -> Self {
+        MigratorConfig {
+            table_name: String::from("migrations"),
+            schema: None,
+            checksum_mismatch: Policy::default(),
+            redo_changed: false,
+            nuclear_debug_from: None,
+            out_of_order: Policy::default(),
+            unknown_applied: Policy::Ignore,
+            lock_key: None,
+            statement_timeout: None,
+            lock_timeout: None,
+            retries: 0,
+            retry_backoff: Duration::from_millis(100),
+            debug_modes: DebugModes::default(),
+            forbid_down: false,
+            require_down: false,
+            drop_extensions: false,
+            new_object_statements: Vec::new(),
+            analyze_touched_tables: false,
+            touched_table_statements: Vec::new(),
+            vars: HashMap::new(),
+            dangerous_statements: Policy::Warn,
+            hostname: detect_hostname(),
+            app_version: None,
+            build_id: None,
+            tag_filter: TagFilter::default(),
+            environment: None,
+            notify_channel: None,
+            follower_timeout: None,
+            follower_poll_interval: Duration::from_secs(1),
+            strict_history: true,
+            migrations_root: None,
+            search_path: None,
+            tracked_schema: None,
+            cancellation: None,
+            deadline: None,
+            batch: None,
+        }
+    }
+}
+
+impl MigratorConfig {
+    /// Set the name of the table that records which migrations have been
+    /// performed. The name is used verbatim, so it must be a valid
+    /// identifier for the database.
+    pub fn table_name(mut self, table_name: &str) -> Self {
+        self.table_name = table_name.to_owned();
+        self
+    }
+
+    /// Set the schema containing the migrations table. The schema must
+    /// already exist.
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_owned());
+        self
+    }
+
+    /// Returns the table name, qualified with the schema if one is set, or
+    /// otherwise with the first schema of the search path if one is set and
+    /// the table isn't shared by tracked schemas
+    pub fn qualified_table_name(&self) -> String {
+        let search_path = self
+            .search_path
+            .as_deref()
+            .filter(|_| self.tracked_schema.is_none())
+            .and_then(|path| path.split(',').next())
+            .map(str::trim);
+        match self.schema.as_deref().or(search_path) {
+            Some(schema) => format!("{}.{}", schema, self.table_name),
+            None => self.table_name.clone(),
+        }
+    }
+
+    /// Set what to do when an applied migration's up statements have changed
+    pub fn checksum_mismatch(mut self, policy: Policy) -> Self {
+        self.checksum_mismatch = policy;
+        self
+    }
+
+    /// Undo and perform again each applied migration whose up statements have
+    /// changed, along with the applied migrations depending on it, instead of
+    /// handling it with `checksum_mismatch`. Meant for development, this only
+    /// takes effect when `debug_modes` is `DebugModes::Execute`, the default
+    /// with `debug_assertions` enabled.
+    pub fn redo_changed(mut self, redo_changed: bool) -> Self {
+        self.redo_changed = redo_changed;
+        self
+    }
+
+    /// Roll back the applied migrations down to and including the migration
+    /// named `name`, then perform them again, like `nuclear_debug` scoped to
+    /// the migrations being worked on. Migrations before it are left alone.
+    /// Like the debug modes, this is controlled by `debug_modes`.
+    pub fn nuclear_debug_from(mut self, name: &str) -> Self {
+        self.nuclear_debug_from = Some(name.to_owned());
+        self
+    }
+
+    /// Set what to do when a pending migration has a lower version than an
+    /// applied migration. When allowed, the pending migration is performed.
+    pub fn out_of_order(mut self, policy: Policy) -> Self {
+        self.out_of_order = policy;
+        self
+    }
+
+    /// Set what to do when migrations have been applied that weren't
+    /// supplied, such as when an older version of the code is deployed after
+    /// a newer one
+    pub fn unknown_applied(mut self, policy: Policy) -> Self {
+        self.unknown_applied = policy;
+        self
+    }
+
+    /// Hold an advisory lock using `key` while migrations run. Other
+    /// processes running migrations with the same key wait for the lock to
+    /// be released before checking which migrations are pending.
+    pub fn lock_key(mut self, key: i64) -> Self {
+        self.lock_key = Some(key);
+        self
+    }
+
+    /// Fail a migration when one of its statements runs for longer than
+    /// `timeout`. The timeout is set at the start of each migration's
+    /// transaction, so it doesn't apply to migrations run with
+    /// `no_transaction`.
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail a migration when one of its statements waits for longer than
+    /// `timeout` to acquire a lock, such as a table lock held by a
+    /// long-running query, instead of stalling until the lock is released.
+    /// Like `statement_timeout`, it doesn't apply to migrations run with
+    /// `no_transaction`.
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry a migration up to `retries` times when it fails because of a
+    /// deadlock, lock timeout or serialization failure, waiting `backoff`
+    /// before the first retry and twice as long before each one after, up to a
+    /// minute.
+    /// Migrations run with `no_transaction` aren't retried, because the
+    /// statements before the failure remain applied.
+    pub fn retry(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Set what to do with migrations in a debug mode, such as to allow
+    /// them in a staging environment built in release mode
+    pub fn debug_modes(mut self, debug_modes: DebugModes) -> Self {
+        self.debug_modes = debug_modes;
+        self
+    }
+
+    /// Set whether anything that would undo a migration, such as a rollback
+    /// or a migration left in a debug mode, returns an error instead of
+    /// executing down statements. Intended to protect production databases.
+    pub fn forbid_down(mut self, forbid: bool) -> Self {
+        self.forbid_down = forbid;
+        self
+    }
+
+    /// Set whether every supplied migration with up statements must also have
+    /// down statements, so that rollbacks and debug modes can't silently
+    /// leave its changes behind
+    pub fn require_down(mut self, require: bool) -> Self {
+        self.require_down = require;
+        self
+    }
+
+    /// Set whether undoing a migration drops the extensions it requires with
+    /// `require_extension`. Extensions are kept by default, as objects outside
+    /// of the migration may depend on them.
+    pub fn drop_extensions(mut self, drop: bool) -> Self {
+        self.drop_extensions = drop;
+        self
+    }
+
+    /// Execute `statement` within each migration for every table, view or
+    /// sequence it created, such as `GRANT SELECT ON {kind} {object} TO
+    /// readonly`. `{object}` is replaced with the object's quoted, qualified
+    /// name, and `{kind}` with `TABLE` or `SEQUENCE`. New objects are found by
+    /// comparing the catalog before and after the migration's up statements.
+    pub fn for_new_objects(mut self, statement: &str) -> Self {
+        self.new_object_statements.push(statement.to_owned());
+        self
+    }
+
+    /// Set whether `ANALYZE` is executed on the tables each migration touched
+    /// once it commits, so that the planner's statistics aren't left stale
+    /// after large schema changes and backfills. Touched tables are those
+    /// named by `CREATE TABLE`, `ALTER TABLE`, `CREATE INDEX`, `INSERT`,
+    /// `UPDATE` and `DELETE` statements, backfilled tables, and those
+    /// declared with `Migration::touches`.
+    pub fn analyze_touched_tables(mut self, analyze: bool) -> Self {
+        self.analyze_touched_tables = analyze;
+        self
+    }
+
+    /// Execute `statement` on each table a migration touched once it
+    /// commits, like `analyze_touched_tables`, such as `VACUUM ANALYZE
+    /// {table}`. `{table}` is replaced with the table's name as written in
+    /// the migration. A failing statement is logged as a warning.
+    pub fn for_touched_tables(mut self, statement: &str) -> Self {
+        self.touched_table_statements.push(statement.to_owned());
+        self
+    }
+
+    /// Substitute each value of `vars` for the `{{name}}` placeholders named
+    /// by its key in migration statements before they're executed, such as
+    /// `{{table_prefix}}`. Placeholders without a value are left as is.
+    /// Checksums are computed from the statements before substitution, so
+    /// they're the same in every environment.
+    pub fn with_vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.vars.extend(vars);
+        self
+    }
+
+    /// Set what to do when a pending migration contains a statement that is
+    /// likely to cause problems in production, such as `CREATE INDEX` without
+    /// `CONCURRENTLY`. The statements are checked before any migration is
+    /// executed.
+    pub fn dangerous_statements(mut self, policy: Policy) -> Self {
+        self.dangerous_statements = policy;
+        self
+    }
+
+    /// Set the host name recorded for each migration performed, such as the
+    /// name of the pod or instance running the migrations
+    pub fn hostname(mut self, hostname: &str) -> Self {
+        self.hostname = Some(hostname.to_owned());
+        self
+    }
+
+    /// Set the application version recorded for each migration performed, such
+    /// as `env!("CARGO_PKG_VERSION")` or a release's commit hash
+    pub fn app_version(mut self, version: &str) -> Self {
+        self.app_version = Some(version.to_owned());
+        self
+    }
+
+    /// Set the identifier of the build recorded for each migration performed,
+    /// such as the git commit hash emitted by `vergen`, so that schema changes
+    /// can be traced back to the release that made them
+    pub fn build_id(mut self, build_id: &str) -> Self {
+        self.build_id = Some(build_id.to_owned());
+        self
+    }
+
+    /// Only perform the pending migrations selected by `filter`, such as
+    /// `TagFilter::exclude("slow")` to leave heavy backfills to a separate
+    /// maintenance job. Migrations that aren't selected are left pending, and
+    /// aren't considered when checking the order of versioned migrations.
+    pub fn tag_filter(mut self, filter: TagFilter) -> Self {
+        self.tag_filter = filter;
+        self
+    }
+
+    /// Set the environment the migrations run in. Pending migrations marked
+    /// with `only_in` other environments, or `skip_in` this environment, are
+    /// left pending like migrations a `tag_filter` doesn't select. Without an
+    /// environment, migrations marked with `only_in` aren't performed.
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Notify listeners on `channel` after a run applies migrations, with the
+    /// name of the last migration applied as the payload, such as so other
+    /// services can refresh prepared statements or caches that depend on the
+    /// schema. Only supported by PostgreSQL.
+    pub fn notify_channel(mut self, channel: &str) -> Self {
+        self.notify_channel = Some(channel.to_owned());
+        self
+    }
+
+    /// Elect a leader among processes running migrations with the same
+    /// `lock_key`: the process that acquires the lock executes the
+    /// migrations, while the others don't queue on the lock and instead check
+    /// every `poll_interval` whether the migrations have been applied,
+    /// returning an empty report once they have. A waiting process tries to
+    /// take over the lock each time it checks, in case the leader exited, and
+    /// returns `MigrationError::FollowerTimeout` after waiting for `timeout`.
+    pub fn leader_election(mut self, timeout: Duration, poll_interval: Duration) -> Self {
+        self.follower_timeout = Some(timeout);
+        self.follower_poll_interval = poll_interval;
+        self
+    }
+
+    /// Set whether failing to read the migrations table returns an error
+    /// unless the table doesn't exist. When disabled, an unreachable database
+    /// is indistinguishable from a new one, and every migration is pending.
+    pub fn strict_history(mut self, strict: bool) -> Self {
+        self.strict_history = strict;
+        self
+    }
+
+    /// Set the directory relative paths given to `with_up_file` and
+    /// `with_down_file` are resolved against
+    pub fn migrations_root<P: AsRef<Path>>(mut self, root: P) -> Self {
+        self.migrations_root = Some(root.as_ref().to_owned());
+        self
+    }
+
+    /// Set the search path of the connection while each migration runs, such
+    /// as `app, public`, so that unqualified names resolve to the intended
+    /// schemas regardless of the role's default. Unless `schema` is set, the
+    /// migrations table is kept in the first schema of the path. Ignored by
+    /// `run_for_schemas` and `verify_replayable`, which set the search path
+    /// themselves. Only supported by PostgreSQL.
+    pub fn search_path(mut self, search_path: &str) -> Self {
+        self.search_path = Some(search_path.to_owned());
+        self
+    }
+
+    /// Set the schema recorded with each migration, keeping a separate
+    /// history for the migrations of `schema` within the migrations table.
+    /// Migrations are only compared against the history of the same schema,
+    /// and undoing every migration of a schema leaves the table in place.
+    /// When combined with `search_path`, set `schema` too, so that the shared
+    /// table isn't looked up within the search path.
+    pub fn tracked_schema(mut self, schema: &str) -> Self {
+        self.tracked_schema = Some(schema.to_owned());
+        self
+    }
+
+    /// Returns the schema recorded with each migration
+    pub(crate) fn recorded_schema(&self) -> &str {
+        self.tracked_schema.as_deref().unwrap_or_default()
+    }
+
+    /// Stop the run once `cancellation` holds true, such as when a deploy is
+    /// aborted. The run stops before the next statement or migration starts,
+    /// rolling back the transaction of the migration in progress, and returns
+    /// `MigrationError::Cancelled`. A statement that is executing isn't
+    /// interrupted, and the statements of a migration outside of a
+    /// transaction that already executed stay applied.
+    pub fn cancel_on(mut self, cancellation: watch::Receiver<bool>) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Stop the run once `deadline` passes, such as the end of a deployment
+    /// window. Like `cancel_on`, the run stops before the next statement or
+    /// migration starts, rolling back the transaction of the migration in
+    /// progress, and returns `MigrationError::DeadlineExceeded`. Use
+    /// `statement_timeout` to also limit how long a single statement may run.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns an error if the run has been cancelled or passed its deadline
+    /// before `migration` finished
+    pub(crate) fn check_interrupted(&self, migration: &str) -> Result<(), MigrationError> {
+        match &self.cancellation {
+            Some(cancellation) if *cancellation.borrow() => {
+                return Err(MigrationError::Cancelled(migration.to_owned()))
+            }
+            _ => {}
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                Err(MigrationError::DeadlineExceeded(migration.to_owned()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `statement` with the `{{name}}` placeholders replaced by their
+    /// values
+    pub(crate) fn substitute_vars(&self, statement: &str) -> String {
+        self.vars
+            .iter()
+            .fold(statement.to_owned(), |statement, (name, value)| {
+                statement.replace(&format!("{{{{{}}}}}", name), value)
+            })
+    }
+}
+
+fn detect_hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_owned())
+        .filter(|hostname| !hostname.is_empty())
+}
@@ -0,0 +1,71 @@
+use super::{MigrationBackend, MigrationError, Migrator};
+use futures_core::future::BoxFuture;
+use sqlx::{
+    pool::{PoolConnection, PoolOptions},
+    Acquire, Connection, Database, Pool, Transaction,
+};
+use std::time::Duration;
+
+/// How long `Migrator::connect` waits for the database to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The single-connection pool created by `Migrator::connect`, owned by the
+/// migrator that uses it
+pub struct MigratorPool<DB: Database>(Pool<DB>);
+
+/// A migrator that manages its own connection to a PostgreSQL database
+#[cfg(feature = "postgres")]
+pub type PgMigrator = Migrator<MigratorPool<sqlx::Postgres>>;
+
+/// A migrator that manages its own connection to a MySQL database
+#[cfg(feature = "mysql")]
+pub type MySqlMigrator = Migrator<MigratorPool<sqlx::MySql>>;
+
+/// A migrator that manages its own connection to a SQLite database
+#[cfg(feature = "sqlite")]
+pub type SqliteMigrator = Migrator<MigratorPool<sqlx::Sqlite>>;
+
+impl<'a, DB: Database> Acquire<'a> for MigratorPool<DB> {
+    type Database = DB;
+
+    type Connection = PoolConnection<DB>;
+
+    fn acquire(self) -> BoxFuture<'a, Result<Self::Connection, sqlx::Error>> {
+        Box::pin(async move { self.0.acquire().await })
+    }
+
+    fn begin(self) -> BoxFuture<'a, Result<Transaction<'a, DB>, sqlx::Error>> {
+        Box::pin(async move { self.0.begin().await })
+    }
+}
+
+impl<DB: MigrationBackend> Migrator<MigratorPool<DB>> {
+    /// Create a migrator that connects to the database at `url`, such as for
+    /// command line tools that don't otherwise need a pool. The database is
+    /// chosen by the migrator's type, such as `PgMigrator::connect(url)`. See
+    /// `connect_with`.
+    pub async fn connect(url: &str) -> Result<Self, MigrationError> {
+        let options = migration_try!(url.parse(), Connection {});
+        Self::connect_with(options).await
+    }
+
+    /// Create a migrator that connects to the database using `options`, such
+    /// as `PgConnectOptions`. The migrator uses a pool of a single
+    /// connection, which is opened before this returns and kept open for as
+    /// long as the migrator runs, however long its migrations take.
+    pub async fn connect_with(
+        options: <DB::Connection as Connection>::Options,
+    ) -> Result<Self, MigrationError> {
+        let pool = migration_try!(
+            PoolOptions::<DB>::new()
+                .max_connections(1)
+                .connect_timeout(CONNECT_TIMEOUT)
+                .idle_timeout(None)
+                .max_lifetime(None)
+                .connect_with(options)
+                .await,
+            Connection {}
+        );
+        Ok(Migrator::new(MigratorPool(pool)))
+    }
+}
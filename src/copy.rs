@@ -0,0 +1,88 @@
+use super::Migration;
+use sqlx::{error::BoxDynError, postgres::PgCopyIn, PgConnection, Postgres};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// The size of the chunks a file is sent to the server in
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Debug)]
+/// The data sent to a `COPY ... FROM STDIN` statement
+pub enum CopyData {
+    /// Data held in memory
+    Bytes(Arc<[u8]>),
+    /// A file that is read when the migration executes
+    File(PathBuf),
+}
+
+impl From<Vec<u8>> for CopyData {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(bytes.into())
+    }
+}
+
+impl From<&'static [u8]> for CopyData {
+    fn from(bytes: &'static [u8]) -> Self {
+        Self::Bytes(bytes.into())
+    }
+}
+
+impl From<PathBuf> for CopyData {
+    fn from(path: PathBuf) -> Self {
+        Self::File(path)
+    }
+}
+
+impl From<&Path> for CopyData {
+    fn from(path: &Path) -> Self {
+        Self::File(path.to_owned())
+    }
+}
+
+impl Migration {
+    /// Add a Postgres `COPY ... FROM STDIN` statement, such as
+    /// `COPY countries FROM STDIN (FORMAT csv)`, that is performed when
+    /// applying the migration, after the "Up" statements added so far. `data`
+    /// is streamed to the server on the migration's connection, within its
+    /// transaction. A file is sent in chunks rather than read into memory.
+    pub fn with_copy(self, statement: &str, data: impl Into<CopyData>) -> Self {
+        let (statement, data) = (statement.to_owned(), data.into());
+        self.with_up_fn::<Postgres, PgConnection, _>(move |conn| {
+            let (statement, data) = (statement.clone(), data.clone());
+            Box::pin(async move {
+                let mut copy = conn.copy_in_raw(&statement).await?;
+                let sent = match &data {
+                    CopyData::Bytes(bytes) => {
+                        copy.send(&bytes[..]).await.map(|_| ()).map_err(Into::into)
+                    }
+                    CopyData::File(path) => send_file(&mut copy, path).await,
+                };
+                match sent {
+                    Ok(()) => {
+                        copy.finish().await?;
+                        Ok(())
+                    }
+                    Err(err) => {
+                        copy.abort(err.to_string()).await?;
+                        Err(err)
+                    }
+                }
+            })
+        })
+    }
+}
+
+async fn send_file(copy: &mut PgCopyIn<&mut PgConnection>, path: &Path) -> Result<(), BoxDynError> {
+    let mut file = std::fs::File::open(path)?;
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(());
+        }
+        copy.send(&chunk[..read]).await?;
+    }
+}
@@ -0,0 +1,186 @@
+use super::{Migration, MigrationError};
+use std::collections::HashSet;
+
+impl Migration {
+    /// Perform this migration only after the migration named `name`, which
+    /// may be supplied in any position, such as by another crate
+    pub fn depends_on(mut self, name: &str) -> Self {
+        self.depends_on.push(name.to_owned());
+        self
+    }
+
+    /// Returns true if this migration is `name` or replaces it
+    fn provides(&self, name: &str) -> bool {
+        self.name == name || self.replaces.iter().any(|replaced| replaced == name)
+    }
+}
+
+/// Moves each migration after the migrations it depends on, otherwise keeping
+/// the order they're in. Migrations caught in a cycle are left in order, to
+/// be reported by `verify`.
+pub(crate) fn sort(migrations: &mut Vec<Migration>) {
+    let mut remaining = std::mem::take(migrations);
+    while !remaining.is_empty() {
+        let ready = remaining
+            .iter()
+            .position(|migration| {
+                migration.depends_on.iter().all(|dependency| {
+                    !remaining
+                        .iter()
+                        .any(|m| m.name != migration.name && m.provides(dependency))
+                })
+            })
+            .unwrap_or(0);
+        migrations.push(remaining.remove(ready));
+    }
+}
+
+/// Checks that every dependency of `migrations` has been performed or comes
+/// before the migration depending on it
+pub(crate) fn verify(
+    migrations: &[Migration],
+    performed_migrations: &HashSet<String>,
+) -> Result<(), MigrationError> {
+    for (index, migration) in migrations.iter().enumerate() {
+        for dependency in migration.depends_on.iter() {
+            if migrations[..index].iter().any(|m| m.provides(dependency)) {
+                continue;
+            }
+            if migrations[index..].iter().any(|m| m.provides(dependency)) {
+                return Err(MigrationError::DependencyCycle(cycle(
+                    migrations, migration,
+                )));
+            }
+            if !performed_migrations.contains(dependency) {
+                return Err(MigrationError::MissingDependency {
+                    migration: migration.name.clone(),
+                    dependency: dependency.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the names of a cycle of dependencies reachable from `start`,
+/// beginning and ending with the same migration
+fn cycle(migrations: &[Migration], start: &Migration) -> Vec<String> {
+    fn visit<'m>(
+        migrations: &'m [Migration],
+        migration: &'m Migration,
+        path: &mut Vec<&'m str>,
+        visited: &mut HashSet<&'m str>,
+    ) -> Option<Vec<String>> {
+        if let Some(position) = path.iter().position(|&name| name == migration.name) {
+            let mut cycle = path[position..]
+                .iter()
+                .map(|&name| name.to_owned())
+                .collect::<Vec<_>>();
+            cycle.push(migration.name.clone());
+            return Some(cycle);
+        }
+        if !visited.insert(&migration.name) {
+            return None;
+        }
+        path.push(&migration.name);
+        for dependency in migration.depends_on.iter() {
+            for next in migrations.iter().filter(|m| m.provides(dependency)) {
+                if let Some(cycle) = visit(migrations, next, path, visited) {
+                    return Some(cycle);
+                }
+            }
+        }
+        path.pop();
+        None
+    }
+    visit(migrations, start, &mut Vec::new(), &mut HashSet::new())
+        .unwrap_or_else(|| vec![start.name.clone()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(migrations: &[Migration]) -> Vec<&str> {
+        migrations.iter().map(|m| m.name.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_migrations_after_their_dependencies() {
+        let mut migrations = vec![
+            Migration::new("c").depends_on("b"),
+            Migration::new("a"),
+            Migration::new("b").depends_on("a"),
+            Migration::new("d"),
+        ];
+        sort(&mut migrations);
+        assert_eq!(names(&migrations), vec!["a", "b", "c", "d"]);
+        assert!(verify(&migrations, &HashSet::new()).is_ok());
+    }
+
+    #[test]
+    fn keeps_the_order_of_independent_migrations() {
+        let mut migrations = vec![
+            Migration::new("b"),
+            Migration::new("a"),
+            Migration::new("c"),
+        ];
+        sort(&mut migrations);
+        assert_eq!(names(&migrations), vec!["b", "a", "c"]);
+    }
+
+    #[test]
+    fn depends_on_the_migrations_a_snapshot_replaces() {
+        let mut migrations = vec![
+            Migration::new("users_index").depends_on("users"),
+            Migration::new("snapshot").replaces(&["users"]),
+        ];
+        sort(&mut migrations);
+        assert_eq!(names(&migrations), vec!["snapshot", "users_index"]);
+        assert!(verify(&migrations, &HashSet::new()).is_ok());
+    }
+
+    #[test]
+    fn reports_missing_dependencies() {
+        let migrations = vec![Migration::new("b").depends_on("a")];
+        match verify(&migrations, &HashSet::new()) {
+            Err(MigrationError::MissingDependency {
+                migration,
+                dependency,
+            }) => {
+                assert_eq!(migration, "b");
+                assert_eq!(dependency, "a");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let performed = std::iter::once(String::from("a")).collect();
+        assert!(verify(&migrations, &performed).is_ok());
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let mut migrations = vec![
+            Migration::new("a").depends_on("c"),
+            Migration::new("b").depends_on("a"),
+            Migration::new("c").depends_on("b"),
+        ];
+        sort(&mut migrations);
+        match verify(&migrations, &HashSet::new()) {
+            Err(MigrationError::DependencyCycle(cycle)) => {
+                assert_eq!(cycle.first(), cycle.last());
+                assert_eq!(cycle.len(), 4);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_a_migration_depending_on_itself() {
+        let migrations = vec![Migration::new("a").depends_on("a")];
+        match verify(&migrations, &HashSet::new()) {
+            Err(MigrationError::DependencyCycle(cycle)) => assert_eq!(cycle, vec!["a", "a"]),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use super::{
+    is_builtin, logging, migration_6_schema, render, DebugModes, Migration, MigrationBackend,
+    MigrationError, MigratorConfig, Mode,
+};
+use std::collections::HashSet;
+
+/// Returns the names of the applied migrations to undo and perform again
+/// because their up statements changed since they were applied, along with
+/// the applied migrations depending on them. Nothing is redone unless
+/// `config.redo_changed` is set and debug modes are executed.
+pub(crate) async fn changed<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    performed_migrations: &HashSet<String>,
+    config: &MigratorConfig,
+) -> Result<HashSet<String>, MigrationError> {
+    let mut changed = HashSet::new();
+    // Checksums are looked up by schema, so tables that haven't been upgraded
+    // are left to be checked by the next run
+    if !config.redo_changed
+        || config.debug_modes != DebugModes::Execute
+        || !performed_migrations.contains(migration_6_schema::NAME)
+    {
+        return Ok(changed);
+    }
+    let table = config.qualified_table_name();
+    // Migrations are sorted after their dependencies, so a single pass finds
+    // the dependents of dependents too
+    for migration in migrations.iter().filter(|m| {
+        !is_builtin(&m.name)
+            && !m.managed
+            && m.mode == Mode::Stable
+            && performed_migrations.contains(&m.name)
+    }) {
+        if migration
+            .depends_on
+            .iter()
+            .any(|dependency| changed.contains(dependency))
+        {
+            logging::warning(&migration.name, "depends on a changed migration, redoing");
+            changed.insert(migration.name.clone());
+            continue;
+        }
+        // Records `dry_run` expects to be renamed or adopted don't exist yet,
+        // and have no checksum to compare
+        let applied = migration_try!(
+            DB::migration_checksum(conn, &table, config.recorded_schema(), &migration.name).await,
+            Bookkeeping {
+                migration: Some(migration.name.clone()),
+                sql: render(DB::SELECT_CHECKSUM, &table),
+            }
+        );
+        if matches!(applied, Some(applied) if applied != migration.checksum()) {
+            logging::warning(&migration.name, "changed since it was applied, redoing");
+            changed.insert(migration.name.clone());
+        }
+    }
+    Ok(changed)
+}
@@ -0,0 +1,106 @@
+use super::{is_builtin, Migration, MigratorConfig};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+};
+
+#[derive(Clone, Debug, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The environment a migrator runs in, selecting the migrations marked with
+/// `only_in` or `skip_in`. Environments are compared by name, so
+/// `Environment::Custom("prod")` is the same environment as
+/// `Environment::Prod`.
+pub enum Environment {
+    Dev,
+    Staging,
+    Prod,
+    /// An environment named by the application, such as `load-test`
+    Custom(String),
+}
+
+impl Environment {
+    /// Returns the environment's name, such as `prod`
+    pub fn name(&self) -> &str {
+        match self {
+            Environment::Dev => "dev",
+            Environment::Staging => "staging",
+            Environment::Prod => "prod",
+            Environment::Custom(name) => name,
+        }
+    }
+}
+
+impl From<&str> for Environment {
+    fn from(name: &str) -> Self {
+        match name {
+            "dev" => Environment::Dev,
+            "staging" => Environment::Staging,
+            "prod" => Environment::Prod,
+            name => Environment::Custom(name.to_owned()),
+        }
+    }
+}
+
+impl PartialEq for Environment {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl Hash for Environment {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name().hash(state);
+    }
+}
+
+impl Display for Environment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Migration {
+    /// Only perform the migration in `environment`, such as debug seeds that
+    /// belong in `Environment::Dev`. Can be called more than once to perform
+    /// the migration in several environments.
+    pub fn only_in(mut self, environment: Environment) -> Self {
+        self.only_in.push(environment);
+        self
+    }
+
+    /// Never perform the migration in `environment`, such as load-test
+    /// fixtures in `Environment::Prod`
+    pub fn skip_in(mut self, environment: Environment) -> Self {
+        self.skip_in.push(environment);
+        self
+    }
+
+    /// Returns true if the migration is performed in `environment`. When no
+    /// environment is configured, migrations marked with `only_in` aren't
+    /// performed.
+    pub fn runs_in(&self, environment: Option<&Environment>) -> bool {
+        match environment {
+            Some(environment) => {
+                (self.only_in.is_empty() || self.only_in.contains(environment))
+                    && !self.skip_in.contains(environment)
+            }
+            None => self.only_in.is_empty(),
+        }
+    }
+}
+
+/// Removes the pending migrations that aren't performed in
+/// `config.environment`. Performed migrations are kept, so their checksums
+/// are still verified.
+pub(crate) fn filter(
+    migrations: &mut Vec<Migration>,
+    performed_migrations: &HashSet<String>,
+    config: &MigratorConfig,
+) {
+    migrations.retain(|migration| {
+        is_builtin(&migration.name)
+            || performed_migrations.contains(&migration.name)
+            || migration.runs_in(config.environment.as_ref())
+    });
+}
@@ -0,0 +1,98 @@
+use super::{Direction, MigrationObserver, Migrator};
+use futures_core::Stream;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The progress of a run, as reported by `MigrationEvents`
+pub enum MigrationEvent {
+    /// A migration started being performed or undone
+    Started {
+        migration: String,
+        direction: Direction,
+    },
+    /// A statement or Rust code step of a migration finished executing.
+    /// `index` starts at 0, out of `total`.
+    StatementCompleted {
+        migration: String,
+        index: usize,
+        total: usize,
+        elapsed: Duration,
+    },
+    /// A migration was performed or undone
+    Finished {
+        migration: String,
+        direction: Direction,
+    },
+    /// A migration failed, and its transaction was rolled back
+    Failed {
+        migration: String,
+        direction: Direction,
+        error: String,
+    },
+}
+
+/// The stream of events of a migrator's run, which ends once the migrator's
+/// run has finished and the migrator is dropped
+pub struct MigrationEvents(mpsc::UnboundedReceiver<MigrationEvent>);
+
+impl Stream for MigrationEvents {
+    type Item = MigrationEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+/// Sends the statements completed to the event stream
+struct EventObserver(mpsc::UnboundedSender<MigrationEvent>);
+
+impl MigrationObserver for EventObserver {
+    fn on_statement_finish(&self, migration: &str, index: usize, total: usize, elapsed: Duration) {
+        let _ = self.0.send(MigrationEvent::StatementCompleted {
+            migration: migration.to_owned(),
+            index,
+            total,
+            elapsed,
+        });
+    }
+}
+
+impl<A> Migrator<A> {
+    /// Returns the migrator along with a stream of events describing each
+    /// migration it performs or undoes, such as to render live progress in a
+    /// dashboard. Events are buffered until they're read, so the stream
+    /// never slows down the run.
+    pub fn with_events(self) -> (Self, MigrationEvents) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (started, finished) = (sender.clone(), sender.clone());
+        let migrator = self
+            .on_before_migration(move |migration, direction| {
+                let _ = started.send(MigrationEvent::Started {
+                    migration: migration.to_owned(),
+                    direction,
+                });
+            })
+            .on_after_migration(move |migration, direction, result| {
+                let migration = migration.to_owned();
+                let _ = finished.send(match result {
+                    Ok(()) => MigrationEvent::Finished {
+                        migration,
+                        direction,
+                    },
+                    Err(err) => MigrationEvent::Failed {
+                        migration,
+                        direction,
+                        error: err.to_string(),
+                    },
+                });
+            })
+            .with_observer(EventObserver(sender));
+        (migrator, MigrationEvents(receiver))
+    }
+}
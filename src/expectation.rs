@@ -0,0 +1,78 @@
+use super::{Migration, MigrationBackend, MigrationError};
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Part of the schema a migration is expected to produce, checked after its
+/// up statements have executed
+pub enum SchemaExpectation {
+    /// The table exists
+    Table { table: String },
+    /// The column exists and has the type, as named by `information_schema`
+    Column {
+        table: String,
+        column: String,
+        data_type: String,
+    },
+}
+
+/// Checks each of the migration's expectations, within its transaction so
+/// that a failure rolls the migration back
+pub(crate) async fn verify<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migration: &Migration,
+) -> Result<(), MigrationError> {
+    let unexpected = |reason: String| MigrationError::UnexpectedSchema {
+        migration: migration.name.clone(),
+        reason,
+    };
+    for expectation in migration.expectations.iter() {
+        match expectation {
+            SchemaExpectation::Table { table } => {
+                if query::<DB>(conn, migration, DB::SELECT_TABLE, &[table])
+                    .await?
+                    .is_none()
+                {
+                    return Err(unexpected(format!("table {} doesn't exist", table)));
+                }
+            }
+            SchemaExpectation::Column {
+                table,
+                column,
+                data_type,
+            } => match query::<DB>(conn, migration, DB::SELECT_COLUMN_TYPE, &[table, column])
+                .await?
+            {
+                Some(actual) if actual.eq_ignore_ascii_case(data_type) => {}
+                Some(actual) => {
+                    return Err(unexpected(format!(
+                        "column {}.{} is {}, not {}",
+                        table, column, actual, data_type
+                    )))
+                }
+                None => {
+                    return Err(unexpected(format!(
+                        "column {}.{} doesn't exist",
+                        table, column
+                    )))
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+async fn query<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migration: &Migration,
+    statement: &str,
+    values: &[&String],
+) -> Result<Option<String>, MigrationError> {
+    let values = values.iter().map(|v| v.as_str()).collect::<Vec<_>>();
+    Ok(migration_try!(
+        DB::query_schema(conn, statement, &values).await,
+        Bookkeeping {
+            migration: Some(migration.name.clone()),
+            sql: statement.to_owned(),
+        }
+    ))
+}
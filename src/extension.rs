@@ -0,0 +1,72 @@
+use super::{plan::Step, Migration, MigrationBackend, MigrationError, MigratorConfig};
+
+impl Migration {
+    /// Create the Postgres extension `extension`, such as `pgcrypto`, if it
+    /// doesn't already exist. Before any migration is executed, the extension
+    /// is checked to be available on the server. Undoing the migration only
+    /// drops the extension when `drop_extensions` is set, as other objects may
+    /// depend on it.
+    pub fn require_extension(mut self, extension: &str) -> Self {
+        self.up.push(format!(
+            "CREATE EXTENSION IF NOT EXISTS {}",
+            quote(extension)
+        ));
+        self.extensions.push(extension.to_owned());
+        self
+    }
+
+    /// Returns the statements undoing this migration, dropping its extensions
+    /// last when `config.drop_extensions` is set. An `irreversible` migration
+    /// has none.
+    pub(crate) fn down_statements(&self, config: &MigratorConfig) -> Vec<String> {
+        if self.irreversible {
+            return Vec::new();
+        }
+        let mut statements = self.down.clone();
+        if config.drop_extensions {
+            statements.extend(
+                self.extensions
+                    .iter()
+                    .rev()
+                    .map(|extension| format!("DROP EXTENSION IF EXISTS {}", quote(extension))),
+            );
+        }
+        statements
+    }
+}
+
+/// Checks that the server can install the extensions required by the
+/// migrations `steps` would perform
+pub(crate) async fn verify_available<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    steps: &[Step],
+) -> Result<(), MigrationError> {
+    for step in steps {
+        let migration = match step {
+            Step::Perform(migration) if !migration.extensions.is_empty() => migration,
+            _ => continue,
+        };
+        let statement = DB::SELECT_AVAILABLE_EXTENSION
+            .ok_or(MigrationError::Unsupported("Requiring extensions"))?;
+        for extension in migration.extensions.iter() {
+            let available = migration_try!(
+                DB::query_schema(conn, statement, &[extension.as_str()]).await,
+                Bookkeeping {
+                    migration: Some(migration.name.clone()),
+                    sql: statement.to_owned(),
+                }
+            );
+            if available.is_none() {
+                return Err(MigrationError::ExtensionUnavailable {
+                    migration: migration.name.clone(),
+                    extension: extension.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn quote(extension: &str) -> String {
+    format!("\"{}\"", extension.replace('"', "\"\""))
+}
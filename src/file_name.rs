@@ -0,0 +1,190 @@
+use super::{logging, render, Migration, MigrationBackend, MigrationError, MigratorConfig};
+use std::collections::HashSet;
+
+impl Migration {
+    /// Create an empty migration named after the source file `path`, such as
+    /// `Migration::new_from_file(file!())`. The path is normalized so that
+    /// the name doesn't change with the build environment: separators become
+    /// `/`, and everything before the crate's `src/` directory is removed.
+    /// Migrations recorded under a name that normalizes to this one, such as
+    /// by `Migration::new(file!())`, are renamed before anything is executed.
+    pub fn new_from_file(path: &str) -> Self {
+        let mut migration = Migration::new(&normalize(path));
+        migration.from_file = true;
+        migration
+    }
+}
+
+/// Returns `path` with `/` separators, starting at its last `src/` directory
+pub(crate) fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./");
+    match path.rfind("/src/") {
+        Some(index) => path[index + 1..].to_owned(),
+        None => path.to_owned(),
+    }
+}
+
+/// Returns the performed names that normalize to the name of a pending
+/// migration created with `new_from_file`, along with that migration
+pub(crate) fn legacy_names<'m>(
+    migrations: &'m [Migration],
+    performed_migrations: &HashSet<String>,
+) -> Vec<(String, &'m Migration)> {
+    let mut legacy = Vec::new();
+    for migration in migrations
+        .iter()
+        .filter(|m| m.from_file && !performed_migrations.contains(&m.name))
+    {
+        if let Some(name) = performed_migrations
+            .iter()
+            .find(|&name| normalize(name) == migration.name)
+        {
+            legacy.push((name.clone(), migration));
+        }
+    }
+    legacy
+}
+
+/// Renames the records of migrations performed under a legacy file name to
+/// their normalized names, returning the updated names of the performed
+/// migrations
+pub(crate) async fn rename_legacy<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    mut performed_migrations: HashSet<String>,
+    config: &MigratorConfig,
+) -> Result<HashSet<String>, MigrationError> {
+    let table = config.qualified_table_name();
+    for (legacy, migration) in legacy_names(migrations, &performed_migrations) {
+        migration_try!(
+            DB::rename_migration(
+                conn,
+                &table,
+                config.recorded_schema(),
+                &legacy,
+                &migration.name
+            )
+            .await,
+            Bookkeeping {
+                migration: Some(migration.name.clone()),
+                sql: render(DB::RENAME_MIGRATION, &table),
+            }
+        );
+        logging::migration_renamed(&legacy, &migration.name);
+        performed_migrations.remove(&legacy);
+        performed_migrations.insert(migration.name.clone());
+    }
+    Ok(performed_migrations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_paths_to_start_at_src() {
+        assert_eq!(
+            normalize("/home/ci/build/app/src/migrations/m0001_accounts.rs"),
+            "src/migrations/m0001_accounts.rs"
+        );
+        assert_eq!(
+            normalize(r"C:\build\app\src\migrations\m0001_accounts.rs"),
+            "src/migrations/m0001_accounts.rs"
+        );
+        assert_eq!(
+            normalize("./src/migrations/m0001_accounts.rs"),
+            "src/migrations/m0001_accounts.rs"
+        );
+        assert_eq!(
+            normalize("/app/src/vendor/src/m0001_accounts.rs"),
+            "src/m0001_accounts.rs"
+        );
+        assert_eq!(normalize("m0001_accounts.rs"), "m0001_accounts.rs");
+    }
+
+    #[test]
+    fn finds_legacy_names_of_pending_migrations() {
+        let migrations = vec![
+            Migration::new_from_file("/app/src/m0001_accounts.rs"),
+            Migration::new_from_file("/app/src/m0002_invoices.rs"),
+            Migration::new("/app/src/m0003_payments.rs"),
+        ];
+        let performed = [
+            r"C:\app\src\m0001_accounts.rs",
+            "src/m0002_invoices.rs",
+            "/app/src/m0003_payments.rs",
+        ]
+        .iter()
+        .map(|&name| name.to_owned())
+        .collect::<HashSet<_>>();
+
+        let legacy = legacy_names(&migrations, &performed)
+            .into_iter()
+            .map(|(legacy, m)| (legacy, m.name.as_str()))
+            .collect::<Vec<_>>();
+        // The second migration was already recorded under its normalized
+        // name, and the third wasn't created with `new_from_file`
+        assert_eq!(
+            legacy,
+            vec![(
+                String::from(r"C:\app\src\m0001_accounts.rs"),
+                "src/m0001_accounts.rs"
+            )]
+        );
+    }
+
+    /// Executed against the PostgreSQL database at `DATABASE_URL` with
+    /// `cargo test -- --ignored`
+    #[cfg(feature = "postgres")]
+    #[test]
+    #[ignore = "requires DATABASE_URL"]
+    fn reports_renamed_records_in_status() {
+        use crate::PgMigrator;
+        use sqlx::Executor;
+
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL isn't set");
+        let table = format!("rename_legacy_{}", std::process::id());
+        let path = "/build/app/src/m0001_accounts.rs";
+        let migrator = |migration: Migration| async {
+            Ok::<_, MigrationError>(
+                PgMigrator::connect(&url)
+                    .await?
+                    .table_name(&table)
+                    .with_migrations(vec![migration.with_up("SELECT 1")]),
+            )
+        };
+        let normalized = || Migration::new_from_file(path);
+        let assert_renamed = |status: crate::MigrationStatus| {
+            let applied = status
+                .applied
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(applied, vec!["src/m0001_accounts.rs"]);
+            assert!(status.pending.is_empty(), "pending: {:?}", status.pending);
+            assert!(status.unknown.is_empty(), "unknown: {:?}", status.unknown);
+            assert!(status.drifted.is_empty(), "drifted: {:?}", status.drifted);
+        };
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(async {
+            migrator(Migration::new(path)).await?.run().await?;
+            // Before the record is renamed
+            assert_renamed(migrator(normalized()).await?.status().await?);
+            migrator(normalized()).await?.run().await?;
+            assert_renamed(migrator(normalized()).await?.status().await?);
+            Ok::<_, MigrationError>(())
+        });
+        runtime.block_on(async {
+            let pool = sqlx::PgPool::connect(&url).await.unwrap();
+            pool.execute(format!("DROP TABLE IF EXISTS {}", table).as_str())
+                .await
+                .unwrap();
+        });
+        result.unwrap();
+    }
+}
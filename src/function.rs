@@ -0,0 +1,53 @@
+use super::{MigrationBackend, MigrationError};
+use futures_core::future::BoxFuture;
+use sqlx::{error::BoxDynError, Database};
+use std::{any::Any, sync::Arc};
+
+type BoxedFn<DB> = Box<
+    dyn for<'c> Fn(&'c mut <DB as Database>::Connection) -> BoxFuture<'c, Result<(), BoxDynError>>
+        + Send
+        + Sync,
+>;
+
+#[derive(Clone)]
+/// Rust code executed as one of a migration's up steps. The function is
+/// stored without its database type so that `Migration` doesn't need to be
+/// generic, and is checked against the backend when executed.
+pub struct MigrationFn {
+    /// The number of up statements executed before this code
+    pub(crate) position: usize,
+    function: Arc<dyn Any + Send + Sync>,
+}
+
+impl MigrationFn {
+    pub(crate) fn new<DB, F>(position: usize, function: F) -> Self
+    where
+        DB: MigrationBackend,
+        F: for<'c> Fn(&'c mut DB::Connection) -> BoxFuture<'c, Result<(), BoxDynError>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let function: BoxedFn<DB> = Box::new(function);
+        MigrationFn {
+            position,
+            function: Arc::new(function),
+        }
+    }
+
+    pub(crate) async fn execute<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        migration: &str,
+    ) -> Result<(), MigrationError> {
+        let error = |source| MigrationError::Code {
+            migration: migration.to_owned(),
+            source,
+        };
+        let function = self
+            .function
+            .downcast_ref::<BoxedFn<DB>>()
+            .ok_or_else(|| error("the code was written for a different database".into()))?;
+        function(conn).await.map_err(error)
+    }
+}
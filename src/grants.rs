@@ -0,0 +1,61 @@
+use super::{Migration, MigrationBackend, MigrationError, MigratorConfig};
+use std::collections::BTreeSet;
+
+/// The names and kinds of the objects in the current schemas
+pub(crate) type Objects = BTreeSet<(String, String)>;
+
+/// Lists the objects in the current schemas before `migration` executes, if
+/// any statements are configured for new objects
+pub(crate) async fn objects_before<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migration: &Migration,
+    config: &MigratorConfig,
+) -> Result<Option<Objects>, MigrationError> {
+    if config.new_object_statements.is_empty() {
+        return Ok(None);
+    }
+    list::<DB>(conn, migration).await.map(Some)
+}
+
+/// Executes the statements configured for new objects against each object
+/// `migration` created
+pub(crate) async fn apply<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migration: &Migration,
+    before: Objects,
+    config: &MigratorConfig,
+) -> Result<(), MigrationError> {
+    let after = list::<DB>(conn, migration).await?;
+    for (object, kind) in after.difference(&before) {
+        for statement in config.new_object_statements.iter() {
+            let statement = statement
+                .replace("{object}", object)
+                .replace("{kind}", kind);
+            migration_try!(
+                DB::execute(conn, &statement).await,
+                Bookkeeping {
+                    migration: Some(migration.name.clone()),
+                    sql: statement,
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn list<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migration: &Migration,
+) -> Result<Objects, MigrationError> {
+    let statement = DB::SELECT_OBJECTS.ok_or(MigrationError::Unsupported(
+        "Executing statements for new objects",
+    ))?;
+    let objects = migration_try!(
+        DB::select_objects(conn, statement).await,
+        Bookkeeping {
+            migration: Some(migration.name.clone()),
+            sql: statement.to_owned(),
+        }
+    );
+    Ok(objects.into_iter().collect())
+}
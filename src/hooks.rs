@@ -0,0 +1,122 @@
+use super::{Direction, MigrationError, PlannedMigration};
+use futures_core::future::BoxFuture;
+use std::time::Duration;
+
+type MigrationHook = Box<dyn Fn(&str, Direction) + Send + Sync>;
+type MigrationOutcomeHook = Box<dyn Fn(&str, Direction, Result<(), &MigrationError>) + Send + Sync>;
+type AllHook = Box<dyn Fn() + Send + Sync>;
+type AllOutcomeHook = Box<dyn Fn(Result<(), &MigrationError>) + Send + Sync>;
+type ConfirmHook = Box<dyn Fn(PlannedMigration) -> BoxFuture<'static, bool> + Send + Sync>;
+
+#[derive(Default)]
+/// The callbacks registered on a `Migrator`
+pub(crate) struct Hooks {
+    pub before_migration: Vec<MigrationHook>,
+    pub after_migration: Vec<MigrationOutcomeHook>,
+    pub before_all: Vec<AllHook>,
+    pub after_all: Vec<AllOutcomeHook>,
+    pub observers: Vec<Box<dyn MigrationObserver>>,
+    pub metrics: Vec<Box<dyn MetricsSink>>,
+    pub confirm: Option<ConfirmHook>,
+}
+
+/// Receives progress while a migration executes, such as to render a progress
+/// bar or heartbeat during a long backfill. `index` counts the migration's
+/// statements and Rust code steps together, starting at 0, out of `total`.
+pub trait MigrationObserver: Send + Sync {
+    /// Called before a statement of `migration` starts executing
+    fn on_statement_start(&self, migration: &str, index: usize, total: usize) {
+        let _ = (migration, index, total);
+    }
+
+    /// Called after a statement of `migration` finishes executing
+    fn on_statement_finish(&self, migration: &str, index: usize, total: usize, elapsed: Duration) {
+        let _ = (migration, index, total, elapsed);
+    }
+}
+
+/// Receives counters and histograms describing executed migrations, to be
+/// exported through a metrics pipeline such as Prometheus or OpenTelemetry.
+/// Each metric is labeled with `migration` and `direction` (`up` or `down`):
+///
+/// - `migrations_applied_total` counts the migrations performed or undone
+/// - `migration_failures_total` counts the migrations that failed
+/// - `migration_duration_seconds` records how long each migration took
+pub trait MetricsSink: Send + Sync {
+    /// Increment the counter `name` by one
+    fn increment_counter(&self, name: &'static str, labels: &[(&'static str, &str)]);
+
+    /// Record `value` in the histogram `name`
+    fn record_histogram(&self, name: &'static str, value: f64, labels: &[(&'static str, &str)]);
+}
+
+impl Hooks {
+    pub fn before_migration(&self, name: &str, direction: Direction) {
+        for hook in self.before_migration.iter() {
+            hook(name, direction);
+        }
+    }
+
+    pub fn after_migration<T>(
+        &self,
+        name: &str,
+        direction: Direction,
+        result: &Result<T, MigrationError>,
+    ) {
+        for hook in self.after_migration.iter() {
+            hook(name, direction, result.as_ref().map(|_| ()));
+        }
+    }
+
+    pub fn record_metrics(
+        &self,
+        name: &str,
+        direction: Direction,
+        result: &Result<Option<Duration>, MigrationError>,
+    ) {
+        let direction = match direction {
+            Direction::Up => "up",
+            Direction::Down => "down",
+        };
+        let labels = [("migration", name), ("direction", direction)];
+        for sink in self.metrics.iter() {
+            match result {
+                Ok(Some(duration)) => {
+                    sink.increment_counter("migrations_applied_total", &labels);
+                    sink.record_histogram(
+                        "migration_duration_seconds",
+                        duration.as_secs_f64(),
+                        &labels,
+                    );
+                }
+                // The migration was skipped because its condition wasn't met
+                Ok(None) => {}
+                Err(_) => sink.increment_counter("migration_failures_total", &labels),
+            }
+        }
+    }
+
+    pub fn statement_started(&self, name: &str, index: usize, total: usize) {
+        for observer in self.observers.iter() {
+            observer.on_statement_start(name, index, total);
+        }
+    }
+
+    pub fn statement_finished(&self, name: &str, index: usize, total: usize, elapsed: Duration) {
+        for observer in self.observers.iter() {
+            observer.on_statement_finish(name, index, total, elapsed);
+        }
+    }
+
+    pub fn before_all(&self) {
+        for hook in self.before_all.iter() {
+            hook();
+        }
+    }
+
+    pub fn after_all<T>(&self, result: &Result<T, MigrationError>) {
+        for hook in self.after_all.iter() {
+            hook(result.as_ref().map(|_| ()));
+        }
+    }
+}
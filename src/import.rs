@@ -0,0 +1,106 @@
+use super::{performed_migrations, Migration, MigrationBackend, MigrationError, MigratorConfig};
+use sqlx::Connection;
+
+#[derive(Debug, Clone)]
+/// A migration recorded as applied by another migration tool
+pub struct ImportedMigration {
+    /// The version the other tool recorded
+    pub version: String,
+    /// The description the other tool recorded, if it records one
+    pub description: Option<String>,
+    /// The name of the supplied migration the entry was matched to, if any.
+    /// Entries without a matching migration are not imported.
+    pub name: Option<String>,
+}
+
+/// Reads another tool's history using `statement` and matches each entry to
+/// one of the supplied migrations
+pub(crate) async fn map_history<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    statement: &str,
+) -> Result<Vec<ImportedMigration>, MigrationError> {
+    let history = migration_try!(
+        DB::select_history(conn, statement).await,
+        Bookkeeping {
+            migration: None,
+            sql: statement.to_owned(),
+        }
+    );
+    Ok(history
+        .into_iter()
+        .map(|(version, description, script)| ImportedMigration {
+            name: matching_migration(migrations, &version, script.as_deref())
+                .map(|m| m.name.clone()),
+            version,
+            description,
+        })
+        .collect())
+}
+
+/// Reads another tool's history using `statement`, then records the supplied
+/// migrations it matches as performed in a single transaction. The
+/// migrations table must already exist.
+pub(crate) async fn import<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    statement: &str,
+    config: &MigratorConfig,
+) -> Result<Vec<ImportedMigration>, MigrationError> {
+    let imported = map_history::<DB>(conn, migrations, statement).await?;
+
+    let performed_migrations = performed_migrations::<DB>(conn, config).await?;
+    let mut tx = migration_try!(
+        conn.begin().await,
+        Transaction {
+            migration: None,
+            sql: String::from("BEGIN"),
+        }
+    );
+    for migration in migrations.iter().filter(|m| {
+        !performed_migrations.contains(&m.name)
+            && imported
+                .iter()
+                .any(|i| i.name.as_deref() == Some(m.name.as_str()))
+    }) {
+        migration.record::<DB>(&mut tx, config, None).await?;
+    }
+    migration_try!(
+        tx.commit().await,
+        Transaction {
+            migration: None,
+            sql: String::from("COMMIT"),
+        }
+    );
+
+    Ok(imported)
+}
+
+/// Finds the migration named after the entry's script file, such as
+/// `V1_2__users` for `V1_2__users.sql`, or otherwise the migration whose name
+/// starts with `version`. Only the digits of versions are compared, so
+/// `20210101120000_users` matches the version `20210101120000`, and
+/// `2021-01-01-120000_users` matches it too.
+fn matching_migration<'m>(
+    migrations: &'m [Migration],
+    version: &str,
+    script: Option<&str>,
+) -> Option<&'m Migration> {
+    if let Some(stem) = script.and_then(|script| script.rsplit('/').next()?.split('.').next()) {
+        if let Some(migration) = migrations.iter().find(|m| m.name == stem) {
+            return Some(migration);
+        }
+    }
+    let version = version_number(version)?;
+    migrations
+        .iter()
+        .find(|m| version_number(m.name.split('_').next().unwrap_or_default()) == Some(version))
+}
+
+fn version_number(version: &str) -> Option<u64> {
+    let digits = version
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>();
+    digits.parse().ok()
+}
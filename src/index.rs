@@ -0,0 +1,57 @@
+use super::Migration;
+use sqlx::{Executor, PgConnection, Postgres};
+
+/// Returns whether the index named `$1` exists but is invalid, such as after
+/// a `CREATE INDEX CONCURRENTLY` that failed or was cancelled
+const SELECT_INVALID_INDEX: &str =
+    "SELECT NOT indisvalid FROM pg_index WHERE indexrelid = to_regclass($1)";
+
+impl Migration {
+    /// Create the Postgres index `index` on `columns` of `table` with
+    /// `CREATE INDEX CONCURRENTLY`, so that writes to the table aren't blocked.
+    /// The migration is executed with `no_transaction`. An invalid index left
+    /// behind by a previous failed attempt is dropped before the index is
+    /// created again, and the down statement drops the index concurrently.
+    pub fn create_index_concurrently(self, index: &str, table: &str, columns: &[&str]) -> Self {
+        self.index_concurrently("INDEX", index, table, columns)
+    }
+
+    /// Create a unique Postgres index concurrently, like
+    /// `create_index_concurrently`
+    pub fn create_unique_index_concurrently(
+        self,
+        index: &str,
+        table: &str,
+        columns: &[&str],
+    ) -> Self {
+        self.index_concurrently("UNIQUE INDEX", index, table, columns)
+    }
+
+    fn index_concurrently(self, kind: &str, index: &str, table: &str, columns: &[&str]) -> Self {
+        let drop = format!("DROP INDEX CONCURRENTLY IF EXISTS {}", index);
+        let create = format!(
+            "CREATE {} CONCURRENTLY IF NOT EXISTS {} ON {} ({})",
+            kind,
+            index,
+            table,
+            columns.join(", ")
+        );
+        let (name, cleanup) = (index.to_owned(), drop.clone());
+        self.with_up_fn::<Postgres, PgConnection, _>(move |conn| {
+            let (name, cleanup) = (name.clone(), cleanup.clone());
+            Box::pin(async move {
+                let invalid = sqlx::query_scalar::<_, bool>(SELECT_INVALID_INDEX)
+                    .bind(&name)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+                if invalid == Some(true) {
+                    conn.execute(cleanup.as_str()).await?;
+                }
+                Ok(())
+            })
+        })
+        .with_up(&create)
+        .with_down(&drop)
+        .no_transaction()
+    }
+}
@@ -1,29 +1,432 @@
+/// Returns the `MigrationError` variant built from the fields given and the
+/// `sqlx::Error` as its source if `$result` is an error. The fields are only
+/// evaluated when there is an error.
+macro_rules! migration_try {
+    ($result:expr, $variant:ident { $($field:ident $(: $value:expr)?),* $(,)? }) => {{
+        match $result {
+            Ok(result) => result,
+            Err(source) => {
+                return Err(MigrationError::$variant {
+                    $($field $(: $value)?,)*
+                    source,
+                })
+            }
+        }
+    }};
+}
+
+/// Builds a `Migration` named `$name` from its up and down statements, which
+/// must be declared together. The down statements are executed in the order
+/// they're listed; pass `down: []` for a migration that can't be undone.
+#[macro_export]
+macro_rules! migration {
+    ($name:expr, up: [$($up:expr),* $(,)?], down: [$($down:expr),* $(,)?] $(,)?) => {{
+        let mut migration = $crate::Migration::new($name)$(.with_up($up))*;
+        migration.down = ::std::vec![$(::std::string::String::from($down)),*];
+        migration
+    }};
+}
+
+mod backend;
+mod backfill;
+mod batch;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod checkpoint;
+mod config;
+mod connect;
+#[cfg(feature = "postgres")]
+mod copy;
+mod dependencies;
+mod drift;
+mod environment;
+mod events;
+mod expectation;
+mod extension;
+mod file_name;
+mod function;
+mod grants;
+mod hooks;
+mod import;
+#[cfg(feature = "postgres")]
+mod index;
+mod lint;
+mod logging;
+mod maintenance;
+mod managed;
 mod migration_0_initial;
+mod migration_1_checksums;
+mod migration_2_statistics;
+mod migration_3_applied_by;
+mod migration_4_build_id;
+mod migration_5_description;
+mod migration_6_schema;
+mod migration_7_batch;
+mod migration_8_repeatable;
+mod migrator;
+mod plan;
+#[doc(hidden)]
+pub mod registry;
+#[cfg(feature = "postgres")]
+mod rename;
+mod repair;
+mod report;
+mod reverse;
+pub mod scaffold;
+mod seed;
+mod settings;
+mod snapshot;
+mod source;
+mod status;
+#[cfg(feature = "table")]
+mod table;
+mod tags;
+#[cfg(feature = "postgres")]
+mod views;
 
-use sqlx::{postgres::PgRow, prelude::*, PgPool};
-use std::collections::HashSet;
+use backend::{render, timeout_statements};
+pub use backend::{AppliedRecord, HistoryRecord, MigrationBackend, MigrationDetails};
+pub use backfill::Backfill;
+pub use checkpoint::Checkpoint;
+pub use config::{DebugModes, MigratorConfig, Policy};
+pub use connect::MigratorPool;
+#[cfg(feature = "mysql")]
+pub use connect::MySqlMigrator;
+#[cfg(feature = "postgres")]
+pub use connect::PgMigrator;
+#[cfg(feature = "sqlite")]
+pub use connect::SqliteMigrator;
+#[cfg(feature = "postgres")]
+pub use copy::CopyData;
+pub use environment::Environment;
+pub use events::{MigrationEvent, MigrationEvents};
+pub use expectation::SchemaExpectation;
+pub use function::MigrationFn;
+use futures_core::future::BoxFuture;
+use hooks::Hooks;
+pub use hooks::{MetricsSink, MigrationObserver};
+pub use import::ImportedMigration;
+pub use lint::DangerousStatement;
+pub use migrator::Migrator;
+pub use plan::{Direction, PlannedMigration};
+#[cfg(feature = "postgres")]
+pub use rename::ColumnRename;
+pub use repair::RepairedChecksum;
+pub use report::{MigrationReport, ReportedMigration, SchemaReport};
+pub use seed::Seed;
+use sha2::{Digest, Sha256};
+pub use source::{MigrationDirectory, MigrationSource, MigrationSourceError};
+use sqlx::{error::BoxDynError, Acquire, Connection};
+#[cfg(feature = "macros")]
+pub use sqlx_simple_migrator_macros::{include_migrations, register_migration};
+pub use status::{AppliedMigration, MigrationStatus};
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+#[cfg(feature = "table")]
+pub use table::{table, Column, ColumnType, ForeignKey, Table, TableDialect};
+pub use tags::TagFilter;
 use thiserror::Error;
 
 #[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// A single database migration
 pub struct Migration {
     pub name: String,
     pub up: Vec<String>,
+    /// Rust code executed between the up statements
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub up_fns: Vec<MigrationFn>,
     pub down: Vec<String>,
+    /// True when a down statement couldn't be derived for a statement added
+    /// with `with_reversible`. The migration is treated as having no down
+    /// statements.
+    pub irreversible: bool,
     pub mode: Mode,
+    /// The version determining the order migrations are executed in
+    pub version: Option<u64>,
+    /// Execute the statements individually instead of within a transaction
+    pub no_transaction: bool,
+    /// Execute each statement in its own transaction instead of the whole
+    /// migration in one
+    pub transaction_per_statement: bool,
+    /// Continue with the remaining up statements when one fails
+    pub continue_on_error: bool,
+    /// A query returning whether the up statements should be executed
+    pub condition: Option<String>,
+    /// Leave the migration unrecorded when its condition isn't met
+    pub retry_when_skipped: bool,
+    /// The schema the up statements are expected to produce
+    pub expectations: Vec<SchemaExpectation>,
+    /// The migrations this migration is equivalent to, such as the
+    /// migrations it was squashed from
+    pub replaces: Vec<String>,
+    /// The Postgres extensions the up statements create
+    pub extensions: Vec<String>,
+    /// Execute the up statements again when they change after the migration
+    /// was applied
+    pub managed: bool,
+    /// Execute the migration after all of the other migrations, as a managed
+    /// migration
+    pub repeatable: bool,
+    /// What the migration does, recorded alongside it for people reading the
+    /// history later
+    pub description: Option<String>,
+    /// Labels used to include or exclude the migration from a run
+    pub tags: Vec<String>,
+    /// When not empty, the migration is only performed in these environments
+    pub only_in: Vec<Environment>,
+    /// The environments the migration is never performed in
+    pub skip_in: Vec<Environment>,
+    /// The migrations that must be performed before this one
+    pub depends_on: Vec<String>,
+    /// The name was normalized from a source file path by `new_from_file`
+    pub from_file: bool,
+    /// The files read into `up` when the migration is run, by the index of
+    /// the statement each replaces
+    pub up_files: Vec<(usize, PathBuf)>,
+    /// The isolation level of the migration's transaction, when it isn't the
+    /// database's default
+    pub isolation: Option<IsolationLevel>,
+    /// The files read into `down` when the migration is run, by the index of
+    /// the statement each replaces, counted from the end
+    pub down_files: Vec<(usize, PathBuf)>,
+    /// The tables the migration changes besides those named by its up
+    /// statements
+    pub touches: Vec<String>,
 }
 
 #[derive(Error, Debug)]
 /// An error executing a migration
-pub struct MigrationError {
-    pub statement: String,
-    pub error: sqlx::Error,
+pub enum MigrationError {
+    /// One of a migration's statements failed to execute
+    #[error(transparent)]
+    Statement(Box<StatementError>),
+    /// A statement maintaining the migrations table, or one the backend
+    /// executes around each migration, failed to execute
+    #[error("Error executing \"{sql}\"{}: {source}", for_migration(.migration))]
+    Bookkeeping {
+        migration: Option<String>,
+        sql: String,
+        #[source]
+        source: sqlx::Error,
+    },
+    /// Beginning, committing or rolling back a transaction failed
+    #[error("Error executing {sql}{}: {source}", for_migration(.migration))]
+    Transaction {
+        migration: Option<String>,
+        sql: String,
+        #[source]
+        source: sqlx::Error,
+    },
+    /// A connection couldn't be acquired from the executor
+    #[error("Error acquiring a connection: {source}")]
+    Connection {
+        #[source]
+        source: sqlx::Error,
+    },
+    /// Acquiring or releasing the advisory lock failed
+    #[error("Error executing \"{sql}\" for the migration lock: {source}")]
+    Lock {
+        sql: String,
+        #[source]
+        source: sqlx::Error,
+    },
+    /// No migration with this name was supplied
+    #[error("No migration named \"{0}\" was supplied")]
+    UnknownMigration(String),
+    /// A migration's Rust code returned an error
+    #[error("Error executing code in migration \"{migration}\": {source}")]
+    Code {
+        migration: String,
+        #[source]
+        source: BoxDynError,
+    },
+    /// Migrations have been applied that weren't supplied
+    #[error("Migrations have been applied that weren't supplied: {}", .0.join(", "))]
+    UnknownApplied(Vec<String>),
+    /// A migration is in a debug mode, but debug modes aren't allowed
+    #[error("Migration \"{0}\" is in a debug mode, which isn't allowed")]
+    DebugMode(String),
+    /// A migration would have been undone, but undoing migrations is
+    /// forbidden
+    #[error("Migration \"{0}\" would be undone, but undoing migrations is forbidden")]
+    DownForbidden(String),
+    /// A migration has no down statements, but every migration is required
+    /// to have them
+    #[error("Migration \"{0}\" has no down statements")]
+    MissingDown(String),
+    /// More than one migration was supplied with the same name, including
+    /// the names of the crate's own migrations
+    #[error("Migration names were supplied more than once: {}", .0.join(", "))]
+    DuplicateName(Vec<String>),
+    /// The operation isn't supported by the database
+    #[error("{0} isn't supported by this database")]
+    Unsupported(&'static str),
+    /// A pending migration has a lower version than an applied migration
+    #[error("Migration \"{migration}\" (version {version}) is pending, but \"{applied}\" (version {applied_version}) has already been applied")]
+    OutOfOrder {
+        migration: String,
+        version: u64,
+        applied: String,
+        applied_version: u64,
+    },
+    /// An applied migration's up statements have changed since it was applied
+    #[error("Migration \"{migration}\" has changed since it was applied (checksum was {applied}, is now {current})")]
+    ChecksumMismatch {
+        migration: String,
+        applied: String,
+        current: String,
+    },
+    /// A migration's up statements didn't produce the expected schema
+    #[error("Migration \"{migration}\" didn't produce the expected schema: {reason}")]
+    UnexpectedSchema { migration: String, reason: String },
+    /// A pending migration contains a statement that is likely to cause
+    /// problems in production
+    #[error("Statement {index} of migration \"{migration}\" is dangerous: {reason}")]
+    DangerousStatement {
+        migration: String,
+        index: usize,
+        reason: &'static str,
+    },
+    /// A migration failed while its migrations were being replayed against
+    /// a throwaway schema
+    #[error("Migration \"{migration}\" isn't replayable, {phase} failed: {source}")]
+    NotReplayable {
+        migration: String,
+        /// `"applying"`, `"undoing"` or `"reapplying"`
+        phase: &'static str,
+        #[source]
+        source: Box<MigrationError>,
+    },
+    /// A `MigrationSource` failed to load its migrations
+    #[error("Error loading migrations: {0}")]
+    Source(#[source] BoxDynError),
+    /// A migration requires an extension that isn't available on the server
+    #[error("Migration \"{migration}\" requires the extension \"{extension}\", which isn't available on this server")]
+    ExtensionUnavailable {
+        migration: String,
+        extension: String,
+    },
+    /// Only some of the migrations a snapshot replaces have been applied, so
+    /// the snapshot can neither be executed nor recorded as performed
+    #[error("Migration \"{migration}\" replaces \"{missing}\", which hasn't been applied")]
+    PartialSnapshot { migration: String, missing: String },
+    /// The database doesn't match the supplied migrations
+    #[error(
+        "The database isn't in sync with the migrations: {} pending, {} unknown, {} changed",
+        .pending.len(),
+        .unknown.len(),
+        .changed.len()
+    )]
+    OutOfSync {
+        /// The supplied migrations that haven't been applied
+        pending: Vec<String>,
+        /// The applied migrations that weren't supplied
+        unknown: Vec<String>,
+        /// The applied migrations whose up statements have changed
+        changed: Vec<String>,
+    },
+    /// Another process held the migration lock, and the migrations weren't
+    /// applied within the follower timeout
+    #[error("Timed out after {0:?} waiting for another process to apply the migrations")]
+    FollowerTimeout(Duration),
+    /// A migration depends on a migration that wasn't supplied and hasn't
+    /// been applied
+    #[error("Migration \"{migration}\" depends on \"{dependency}\", which wasn't supplied")]
+    MissingDependency {
+        migration: String,
+        dependency: String,
+    },
+    /// Migrations depend on each other, so they can't be ordered
+    #[error("Migrations depend on each other: {}", .0.join(" -> "))]
+    DependencyCycle(Vec<String>),
+    /// The confirmation callback declined a destructive migration or undo
+    #[error("Migration \"{0}\" wasn't confirmed")]
+    NotConfirmed(String),
+    /// The run was cancelled before the migration finished. Its transaction
+    /// was rolled back.
+    #[error("Cancelled before migration \"{0}\" finished")]
+    Cancelled(String),
+    /// The run passed its deadline before the migration finished. Its
+    /// transaction was rolled back.
+    #[error("The deadline passed before migration \"{0}\" finished")]
+    DeadlineExceeded(String),
+    /// A setting loaded by `MigratorConfig::from_env` or `from_file` is
+    /// invalid
+    #[error("Invalid migrator configuration: {0}")]
+    Config(String),
+}
+
+#[derive(Error, Debug)]
+#[error(
+    "Error executing statement {index} of {count} of migration \"{migration}\"{} (\"{sql}\"): {source}",
+    described(.description)
+)]
+/// A statement of a migration that failed to execute
+pub struct StatementError {
+    pub migration: String,
+    pub description: Option<String>,
+    /// The statements before this index were executed successfully
+    pub index: usize,
+    /// The number of statements in the migration
+    pub count: usize,
+    pub sql: String,
+    #[source]
+    pub source: sqlx::Error,
 }
 
-#[derive(PartialEq, Clone)]
+impl MigrationError {
+    /// Returns true if the error was caused by a transient condition, such as
+    /// a deadlock, lock timeout or serialization failure, that may succeed
+    /// when retried
+    pub(crate) fn is_transient<DB: MigrationBackend>(&self) -> bool {
+        let source = match self {
+            MigrationError::Statement(err) => &err.source,
+            MigrationError::Bookkeeping { source, .. }
+            | MigrationError::Transaction { source, .. } => source,
+            _ => return false,
+        };
+        match source {
+            sqlx::Error::Database(err) => err
+                .code()
+                .is_some_and(|code| DB::TRANSIENT_ERROR_CODES.contains(&code.as_ref())),
+            _ => false,
+        }
+    }
+}
+
+/// Formats the description of the migration an error occurred in, if any
+fn described(description: &Option<String>) -> String {
+    match description {
+        Some(description) => format!(" ({})", description),
+        None => String::new(),
+    }
+}
+
+/// Formats the migration an error occurred in, if any
+fn for_migration(migration: &Option<String>) -> String {
+    match migration {
+        Some(migration) => format!(" for migration \"{}\"", migration),
+        None => String::new(),
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+/// Whether a column added by `Migration::add_column` allows `NULL`
+pub enum Nullability {
+    Nullable,
+    NotNull,
+}
+
+#[derive(PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// The migration's execution mode
 pub enum Mode {
     /// The migration is stable and ready for deployment
+    #[default]
     Stable,
     /// The migration is still being worked on and should not be deployed
     Debug,
@@ -33,34 +436,24 @@ pub enum Mode {
     NuclearDebug,
 }
 
-impl Default for Mode {
-    fn default() -> Self {
-        Mode::Stable
-    }
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The isolation level of a migration's transaction
+pub enum IsolationLevel {
+    ReadUncommitted,
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
 }
 
-macro_rules! migration_try {
-    ($condition:expr, $stmt:expr) => {{
-        match $condition {
-            Ok(result) => result,
-            Err(err) => {
-                return Err(MigrationError {
-                    statement: $stmt.to_owned(),
-                    error: err,
-                })
-            }
+impl IsolationLevel {
+    fn sql(self) -> &'static str {
+        match self {
+            IsolationLevel::ReadUncommitted => "READ UNCOMMITTED",
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
         }
-    }};
-}
-
-use std::fmt::{Display, Formatter};
-impl Display for MigrationError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Error executing sql \"{}\": {}",
-            self.statement, self.error
-        )
     }
 }
 
@@ -68,8 +461,7 @@ impl Migration {
     /// Create an empty migration. `name` is used as a unique key to check if
     /// the migration has been completed already. If you are using
     /// `std::file!()` make sure to not change your build paths between
-    /// deployments, or normalize the paths before passing them in as the
-    /// migration name.
+    /// deployments, or use `new_from_file` to normalize the paths.
     pub fn new(name: &str) -> Self {
         Migration {
             name: name.to_owned(),
@@ -77,147 +469,1197 @@ impl Migration {
         }
     }
 
+    /// Describe what the migration does, such as `adds invoices table for
+    /// billing v2`. The description is recorded in the migrations table,
+    /// reported by `status` and included in errors executing the migration.
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
     /// Add an "Up" sql statement that is performed when applying the migration
     pub fn with_up(mut self, up: &str) -> Self {
         self.up.push(up.to_owned());
         self
     }
 
+    /// Add Rust code that is performed when applying the migration, after the
+    /// "Up" statements added so far. The code is executed on the migration's
+    /// connection, within its transaction. Only the sql statements are
+    /// included in the migration's checksum.
+    pub fn with_up_fn<DB, C, F>(mut self, function: F) -> Self
+    where
+        DB: MigrationBackend<Connection = C>,
+        C: Connection<Database = DB>,
+        F: for<'c> Fn(&'c mut C) -> BoxFuture<'c, Result<(), BoxDynError>> + Send + Sync + 'static,
+    {
+        self.up_fns
+            .push(MigrationFn::new::<DB, F>(self.up.len(), function));
+        self
+    }
+
+    /// Add a `Backfill` that is performed when applying the migration, after
+    /// the "Up" statements added so far. Each batch is committed separately
+    /// when the migration is executed with `no_transaction`; otherwise the
+    /// batches are executed within the migration's transaction.
+    pub fn with_backfill<DB, C>(self, backfill: Backfill) -> Self
+    where
+        DB: MigrationBackend<Connection = C>,
+        C: Connection<Database = DB>,
+    {
+        self.touches(&backfill.table)
+            .with_up_fn::<DB, C, _>(move |conn| {
+                let backfill = backfill.clone();
+                Box::pin(async move { Ok(backfill.run::<DB>(conn).await?) })
+            })
+    }
+
     /// Add a "Down" sql statement that is performed when rolling a migration back
     pub fn with_down(mut self, down: &str) -> Self {
         self.down.insert(0, down.to_owned());
         self
     }
 
-    /// Mark this migration as executing in debug mode. Will panic if `#[cfg(not(debug_assertions))]`
+    /// Add an "Up" sql statement along with the "Down" statement that undoes
+    /// it, derived from the statement itself. `CREATE TABLE`, `CREATE INDEX`
+    /// and `ALTER TABLE ... ADD COLUMN` statements creating a single named
+    /// object can be reversed. If `up` can't be reversed, it's added on its own
+    /// and the migration is marked as `irreversible`.
+    pub fn with_reversible(mut self, up: &str) -> Self {
+        match reverse::reverse(up) {
+            Some(down) => self.with_up(up).with_down(&down),
+            None => {
+                self.irreversible = true;
+                self.with_up(up)
+            }
+        }
+    }
+
+    /// Add a column of `column_type`, such as `TEXT` or `INTEGER DEFAULT 0`, to
+    /// `table` as an "Up" statement, and drop it as a "Down" statement. A
+    /// `NotNull` column needs a default when the table already has rows.
+    pub fn add_column(
+        self,
+        table: &str,
+        column: &str,
+        column_type: &str,
+        nullability: Nullability,
+    ) -> Self {
+        let not_null = match nullability {
+            Nullability::Nullable => "",
+            Nullability::NotNull => " NOT NULL",
+        };
+        self.with_up(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}{}",
+            table, column, column_type, not_null
+        ))
+        .with_down(&format!("ALTER TABLE {} DROP COLUMN {}", table, column))
+    }
+
+    /// Set the version of this migration. Migrations are executed in order of
+    /// their versions, followed by any migrations without a version in the
+    /// order they were supplied.
+    pub fn version(mut self, version: u64) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Execute this migration's statements individually, outside of a
+    /// transaction. This is required for statements such as
+    /// `CREATE INDEX CONCURRENTLY` that can't run inside a transaction block.
+    /// If a statement fails, the statements before it remain applied and the
+    /// migration isn't recorded as performed.
+    pub fn no_transaction(mut self) -> Self {
+        self.no_transaction = true;
+        self
+    }
+
+    /// Execute each statement in its own transaction, committing it before
+    /// the next statement begins, such as for a long data migration where a
+    /// single transaction would hold its locks and prevent vacuuming until
+    /// the end. The migration is recorded once the last statement succeeds.
+    /// If a statement fails, the statements before it remain applied. Rust
+    /// code, including a `Backfill`, runs outside of the transactions, so a
+    /// backfill commits each batch.
+    pub fn transaction_per_statement(mut self) -> Self {
+        self.transaction_per_statement = true;
+        self
+    }
+
+    /// Returns whether the whole migration is executed within one
+    /// transaction
+    pub(crate) fn single_transaction(&self) -> bool {
+        !self.no_transaction && !self.transaction_per_statement
+    }
+
+    /// Returns whether each statement is executed within its own transaction
+    pub(crate) fn statement_transactions(&self) -> bool {
+        self.transaction_per_statement && !self.no_transaction
+    }
+
+    /// Execute the migration's transaction, in either direction, at the
+    /// isolation level `level` instead of the database's default. A
+    /// `Serializable` migration that fails to serialize can be retried with
+    /// `retries`. The level is ignored with `no_transaction`, and applies to
+    /// each transaction with `transaction_per_statement`.
+    pub fn isolation(mut self, level: IsolationLevel) -> Self {
+        self.isolation = Some(level);
+        self
+    }
+
+    /// Returns the statements executed at the start of the migration's
+    /// transaction
+    pub(crate) fn transaction_statements<DB: MigrationBackend>(
+        &self,
+        config: &MigratorConfig,
+    ) -> Result<Vec<String>, MigrationError> {
+        let mut statements = Vec::new();
+        if let Some(level) = self.isolation {
+            let statement = DB::SET_ISOLATION_LEVEL
+                .ok_or(MigrationError::Unsupported("Setting the isolation level"))?;
+            statements.push(statement.replace("{level}", level.sql()));
+        }
+        if let Some(search_path) = &config.search_path {
+            let statement = DB::SET_LOCAL_SEARCH_PATH
+                .ok_or(MigrationError::Unsupported("Setting the search path"))?;
+            statements.push(statement.replace("{schema}", search_path));
+        }
+        statements.extend(timeout_statements::<DB>(config));
+        Ok(statements)
+    }
+
+    /// Returns the statements executed on the connection before and after the
+    /// migration, setting the configured search path for the session when
+    /// the migration isn't executed within a transaction
+    pub(crate) fn session_statements<DB: MigrationBackend>(
+        &self,
+        config: &MigratorConfig,
+    ) -> Result<(Vec<String>, Vec<String>), MigrationError> {
+        let mut before = DB::BEFORE_MIGRATION
+            .iter()
+            .map(|&s| s.to_owned())
+            .collect::<Vec<_>>();
+        let mut after = Vec::new();
+        if let (true, Some(search_path)) = (self.no_transaction, &config.search_path) {
+            let (set, reset) = match (DB::SET_SEARCH_PATH, DB::RESET_SEARCH_PATH) {
+                (Some(set), Some(reset)) => (set, reset),
+                _ => return Err(MigrationError::Unsupported("Setting the search path")),
+            };
+            before.push(set.replace("{schema}", search_path));
+            after.push(reset.to_owned());
+        }
+        after.extend(DB::AFTER_MIGRATION.iter().map(|&s| s.to_owned()));
+        Ok((before, after))
+    }
+
+    /// Continue with the remaining up statements when one fails, logging a
+    /// warning instead of failing the migration. Within a transaction, each
+    /// statement is executed within a savepoint so that a failed statement
+    /// doesn't abort the rest of the migration. Intended for idempotent
+    /// migrations written with `IF NOT EXISTS`-style statements.
+    pub fn continue_on_error(mut self) -> Self {
+        self.continue_on_error = true;
+        self
+    }
+
+    /// Only execute the up statements when `condition`, a query returning a
+    /// single boolean, returns true. Otherwise the migration is skipped, but
+    /// still recorded as performed unless `retry_when_skipped` is set. The
+    /// condition is queried within the migration's transaction.
+    pub fn run_if_sql(mut self, condition: &str) -> Self {
+        self.condition = Some(condition.to_owned());
+        self
+    }
+
+    /// Check that `table` exists after the up statements have executed,
+    /// failing the migration if it doesn't
+    pub fn expect_table(mut self, table: &str) -> Self {
+        self.expectations.push(SchemaExpectation::Table {
+            table: table.to_owned(),
+        });
+        self
+    }
+
+    /// Check that `table.column` exists and has `data_type` after the up
+    /// statements have executed, failing the migration if it doesn't. Types
+    /// are compared case-insensitively against `information_schema`'s names,
+    /// such as `numeric` or `character varying` on Postgres.
+    pub fn expect_column(mut self, table: &str, column: &str, data_type: &str) -> Self {
+        self.expectations.push(SchemaExpectation::Column {
+            table: table.to_owned(),
+            column: column.to_owned(),
+            data_type: data_type.to_owned(),
+        });
+        self
+    }
+
+    /// Leave the migration unrecorded when it's skipped by its `run_if_sql`
+    /// condition, so that the condition is checked again on the next run
+    pub fn retry_when_skipped(mut self) -> Self {
+        self.retry_when_skipped = true;
+        self
+    }
+
+    /// Mark this migration as executing in debug mode. Whether debug modes
+    /// are executed is controlled by `MigratorConfig::debug_modes`.
     pub fn debug(mut self) -> Self {
-        #[cfg(not(debug_assertions))]
-        panic!("Debug migration turned on");
         self.mode = Mode::Debug;
         self
     }
 
-    /// Mark this migration as executing in "nuclear" debug mode, forcing all migrations to-rerun. Will panic if `#[cfg(not(debug_assertions))]`
+    /// Mark this migration as executing in "nuclear" debug mode, forcing all
+    /// migrations to re-run. Whether debug modes are executed is controlled by
+    /// `MigratorConfig::debug_modes`.
     pub fn nuclear_debug(mut self) -> Self {
-        #[cfg(not(debug_assertions))]
-        panic!("Debug migration turned on");
         self.mode = Mode::NuclearDebug;
         self
     }
 
-    /// Execute all of the migrations against the PgPool provided.
-    pub async fn run_all(
-        pool: &PgPool,
-        mut supplied_migrations: Vec<Migration>,
-    ) -> Result<(), MigrationError> {
-        let mut migrations = vec![migration_0_initial::migration()];
-        migrations.append(&mut supplied_migrations);
-        let mut performed_migrations: HashSet<String> = HashSet::new();
-        sqlx::query("SELECT name FROM migrations")
-            .map(|row: PgRow| {
-                performed_migrations.insert(row.get("name"));
-            })
-            .fetch_all(pool)
+    /// Returns the SHA-256 of the migration's up statements, as a hex string
+    pub fn checksum(&self) -> String {
+        let mut hasher = Sha256::new();
+        for statement in self.up.iter() {
+            hasher.update(statement.as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// The number of up statements and Rust functions
+    fn statement_count(&self) -> i64 {
+        (self.up.len() + self.up_fns.len()) as i64
+    }
+
+    /// The checksum stored when performing this migration. The built-in
+    /// migrations run before the checksum column exists.
+    fn recorded_checksum(&self) -> Option<String> {
+        if is_builtin(&self.name) {
+            None
+        } else {
+            Some(self.checksum())
+        }
+    }
+
+    /// Execute all of the migrations using the executor provided. This can
+    /// be a pool, a connection, or an existing transaction. When given a
+    /// transaction, each migration is executed within a savepoint. Use
+    /// `Migrator` to configure how migrations are executed. Returns what was
+    /// done to each migration.
+    pub async fn run_all<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<MigrationReport, MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .run()
             .await
-            .unwrap_or_default();
-
-        if matches!(
-            migrations.iter().find(|m| Mode::NuclearDebug == m.mode),
-            Some(_)
-        ) {
-            // If any migration is nuclear, roll everything back, then execute all the migraitons again
-            let mut reverse_migrations = migrations.clone();
-            reverse_migrations.reverse();
-
-            for migration in reverse_migrations {
-                migration.undo(&pool).await?;
-                performed_migrations.remove(&migration.name);
-            }
-            for migration in migrations {
-                migration.perform(&pool).await?;
-            }
+    }
+
+    /// Execute the migrations selected by `filter` using the executor
+    /// provided, such as every migration not tagged `slow`. Migrations that
+    /// aren't selected are left pending.
+    pub async fn run_all_filtered<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+        filter: TagFilter,
+    ) -> Result<MigrationReport, MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .tag_filter(filter)
+            .run()
+            .await
+    }
+
+    /// Execute the pending pre-deploy migrations using the executor provided,
+    /// leaving the post-deploy migrations pending
+    pub async fn run_pre_deploy<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<MigrationReport, MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .run_pre_deploy()
+            .await
+    }
+
+    /// Execute all of the pending migrations using the executor provided,
+    /// including the post-deploy migrations
+    pub async fn run_post_deploy<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<MigrationReport, MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .run_post_deploy()
+            .await
+    }
+
+    /// Execute all of the migrations using the executor provided, like
+    /// `run_all`, returning the future of the run along with a stream of its
+    /// events. The run only starts once the future is awaited.
+    pub fn run_all_with_events<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> (
+        impl std::future::Future<Output = Result<MigrationReport, MigrationError>> + 'a,
+        MigrationEvents,
+    )
+    where
+        A: Acquire<'a> + 'a,
+        A::Database: MigrationBackend,
+    {
+        let (migrator, events) = Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .with_events();
+        (migrator.run(), events)
+    }
+
+    /// Execute all of the migrations using the executor provided, stopping
+    /// with `MigrationError::DeadlineExceeded` if the run takes longer than
+    /// `timeout`. The migration in progress when the deadline passes is
+    /// rolled back, and the migrations performed before it remain applied.
+    pub async fn run_all_with_timeout<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+        timeout: Duration,
+    ) -> Result<MigrationReport, MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .deadline(Instant::now() + timeout)
+            .run()
+            .await
+    }
+
+    /// Execute all of the migrations against each of `schemas`, such as one
+    /// schema per tenant, using the executor provided. Each schema is made the
+    /// connection's search path while its migrations run, and its migrations
+    /// are recorded under its name in the shared migrations table. A failure
+    /// in one schema doesn't prevent the remaining schemas from being
+    /// migrated.
+    pub async fn run_all_for_schemas<'a, A>(
+        executor: A,
+        schemas: &[&str],
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<Vec<SchemaReport>, MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .run_for_schemas(schemas)
+            .await
+    }
+
+    /// Replay all of the migrations against a throwaway schema using the
+    /// executor provided: every up is applied, every down is undone in
+    /// reverse, then every up is applied again. A failure while reapplying
+    /// usually means the previous down left something behind.
+    pub async fn verify_replayable<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<(), MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .verify_replayable()
+            .await
+    }
+
+    /// Returns true if any of the migrations hasn't been applied, using the
+    /// executor provided, without creating tables or taking locks
+    pub async fn has_pending_migrations<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<bool, MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .has_pending_migrations()
+            .await
+    }
+
+    /// Checks that the database is in sync with the migrations using the
+    /// executor provided, returning `MigrationError::OutOfSync` otherwise
+    pub async fn check<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<(), MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .check()
+            .await
+    }
+
+    /// Replace the recorded checksums of the changed migrations using the
+    /// executor provided
+    pub async fn repair<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<Vec<RepairedChecksum>, MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .repair()
+            .await
+    }
+
+    /// Run the down scripts to undo all the migrations using the executor
+    /// provided.
+    pub async fn undo_all<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<(), MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .undo_all()
+            .await
+    }
+
+    /// Undo the migrations executed by the last run, such as the last
+    /// deploy, using the executor provided
+    pub async fn rollback_last_batch<'a, A>(
+        executor: A,
+        supplied_migrations: Vec<Migration>,
+    ) -> Result<(), MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        Migrator::new(executor)
+            .with_migrations(supplied_migrations)
+            .rollback_last_batch()
+            .await
+    }
+
+    async fn perform<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        config: &MigratorConfig,
+        hooks: &Hooks,
+    ) -> Result<bool, MigrationError> {
+        let (before, after) = self.session_statements::<DB>(config)?;
+        execute_all::<DB, _>(conn, &self.name, &before).await?;
+        let result = self.perform_transaction::<DB>(conn, config, hooks).await;
+        let after = execute_all::<DB, _>(conn, &self.name, &after).await;
+        let performed = result?;
+        after?;
+        if performed {
+            maintenance::run::<DB>(conn, self, config).await;
+        }
+        Ok(performed)
+    }
+
+    /// Performs the migration, returning false if it was skipped because its
+    /// condition wasn't met
+    async fn perform_transaction<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        config: &MigratorConfig,
+        hooks: &Hooks,
+    ) -> Result<bool, MigrationError> {
+        let started = Instant::now();
+        logging::migration_started(&self.name, Direction::Up);
+        let performed = if !self.single_transaction() {
+            self.perform_statements::<DB>(conn, false, started, config, hooks)
+                .await?
         } else {
-            for migration in migrations {
-                if let Mode::Debug = migration.mode {
-                    migration.undo(&pool).await?;
-                    performed_migrations.remove(&migration.name);
+            let mut tx = migration_try!(
+                conn.begin().await,
+                Transaction {
+                    migration: Some(self.name.clone()),
+                    sql: String::from("BEGIN"),
+                }
+            );
+            execute_all::<DB, _>(
+                &mut tx,
+                &self.name,
+                &self.transaction_statements::<DB>(config)?,
+            )
+            .await?;
+            let performed = self
+                .perform_statements::<DB>(&mut tx, true, started, config, hooks)
+                .await?;
+            migration_try!(
+                tx.commit().await,
+                Transaction {
+                    migration: Some(self.name.clone()),
+                    sql: String::from("COMMIT"),
                 }
+            );
+            performed
+        };
+        if performed {
+            logging::migration_finished(&self.name, Direction::Up, started.elapsed());
+        } else {
+            logging::migration_skipped(&self.name);
+        }
+        Ok(performed)
+    }
 
-                if !performed_migrations.contains(&migration.name) {
-                    migration.perform(&pool).await?;
+    /// Executes the up statements and records the migration if its condition
+    /// is met, otherwise only records it unless `retry_when_skipped` is set
+    async fn perform_statements<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        in_transaction: bool,
+        started: Instant,
+        config: &MigratorConfig,
+        hooks: &Hooks,
+    ) -> Result<bool, MigrationError> {
+        if let Some(condition) = &self.condition {
+            let condition = config.substitute_vars(condition);
+            let met = migration_try!(
+                DB::query_condition(conn, &condition).await,
+                Bookkeeping {
+                    migration: Some(self.name.clone()),
+                    sql: condition,
+                }
+            );
+            if !met {
+                if !self.retry_when_skipped {
+                    self.record::<DB>(conn, config, None).await?;
                 }
+                return Ok(false);
             }
         }
+        let objects = if is_builtin(&self.name) {
+            None
+        } else {
+            grants::objects_before::<DB>(conn, self, config).await?
+        };
+        execute_statements::<DB>(conn, self, Direction::Up, in_transaction, config, hooks).await?;
+        if let Some(objects) = objects {
+            grants::apply::<DB>(conn, self, objects, config).await?;
+        }
+        expectation::verify::<DB>(conn, self).await?;
+        if self.managed {
+            // The previous definition's record is replaced
+            self.remove_record::<DB>(conn, config).await?;
+        }
+        self.record::<DB>(conn, config, Some(started.elapsed()))
+            .await?;
+        Ok(true)
+    }
 
+    /// Records this migration as performed, along with how long it took to
+    /// execute when it was executed
+    async fn record<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        config: &MigratorConfig,
+        duration: Option<Duration>,
+    ) -> Result<(), MigrationError> {
+        let table = config.qualified_table_name();
+        let checksum = self.recorded_checksum();
+        let details = checksum.as_deref().map(|checksum| MigrationDetails {
+            checksum,
+            duration_ms: duration.map(|duration| duration.as_millis() as i64),
+            statement_count: self.statement_count(),
+            hostname: config.hostname.as_deref(),
+            app_version: config.app_version.as_deref(),
+            build_id: config.build_id.as_deref(),
+            description: self.description.as_deref(),
+            schema: config.recorded_schema(),
+            batch: duration.and(config.batch),
+            repeatable: self.repeatable,
+        });
+        migration_try!(
+            DB::record_migration(conn, &table, &self.name, details).await,
+            Bookkeeping {
+                migration: Some(self.name.clone()),
+                sql: render(
+                    if details.is_some() {
+                        DB::INSERT_MIGRATION_WITH_DETAILS
+                    } else {
+                        DB::INSERT_MIGRATION
+                    },
+                    &table
+                ),
+            }
+        );
         Ok(())
     }
 
-    /// Run the down scripts to undo all the migrations
-    pub async fn undo_all(
-        pool: &PgPool,
-        mut supplied_migrations: Vec<Migration>,
-    ) -> Result<(), MigrationError> {
-        let mut migrations = vec![migration_0_initial::migration()];
-        migrations.append(&mut supplied_migrations);
-        let mut performed_migrations: HashSet<String> = HashSet::new();
-        sqlx::query("SELECT name FROM migrations")
-            .map(|row: PgRow| {
-                performed_migrations.insert(row.get("name"));
-            })
-            .fetch_all(pool)
-            .await
-            .unwrap_or_default();
+    /// Compares the checksum recorded when this migration was applied against
+    /// its current up statements. Migrations applied before checksums were
+    /// recorded have their current checksum stored instead. Returns true if
+    /// this is a managed migration that changed and should be performed
+    /// again.
+    async fn verify_checksum<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        config: &MigratorConfig,
+    ) -> Result<bool, MigrationError> {
+        let table = config.qualified_table_name();
+        let current = self.checksum();
+        let applied = migration_try!(
+            DB::migration_checksum(conn, &table, config.recorded_schema(), &self.name).await,
+            Bookkeeping {
+                migration: Some(self.name.clone()),
+                sql: render(DB::SELECT_CHECKSUM, &table),
+            }
+        );
+        match applied {
+            None => {
+                migration_try!(
+                    DB::update_checksum(
+                        conn,
+                        &table,
+                        config.recorded_schema(),
+                        &self.name,
+                        &current
+                    )
+                    .await,
+                    Bookkeeping {
+                        migration: Some(self.name.clone()),
+                        sql: render(DB::UPDATE_CHECKSUM, &table),
+                    }
+                );
+            }
+            Some(applied) if applied != current && self.managed => return Ok(true),
+            Some(applied) if applied != current => match config.checksum_mismatch {
+                Policy::Error => {
+                    return Err(MigrationError::ChecksumMismatch {
+                        migration: self.name.clone(),
+                        applied,
+                        current,
+                    })
+                }
+                Policy::Warn => {
+                    logging::warning(&self.name, "changed since it was applied");
+                }
+                Policy::Ignore => {}
+            },
+            Some(_) => {}
+        }
+        Ok(false)
+    }
 
-        // Undo them in reverse order
-        migrations.reverse();
-        for migration in migrations
-            .into_iter()
+    /// Checks that no pending migration has a lower version than an applied
+    /// migration
+    fn verify_order(
+        migrations: &[Migration],
+        performed_migrations: &HashSet<String>,
+        config: &MigratorConfig,
+    ) -> Result<(), MigrationError> {
+        let newest_applied = migrations
+            .iter()
             .filter(|m| performed_migrations.contains(&m.name))
+            .filter_map(|m| Some((m, m.version?)))
+            .max_by_key(|(_, version)| *version);
+        let (applied, applied_version) = match newest_applied {
+            Some(newest_applied) => newest_applied,
+            None => return Ok(()),
+        };
+        // Post-deploy migrations are expected to be performed after the
+        // pre-deploy migrations that follow them
+        for migration in migrations
+            .iter()
+            .filter(|m| !performed_migrations.contains(&m.name) && !m.is_post_deploy())
         {
-            migration.undo(&pool).await?;
+            let version = match migration.version {
+                Some(version) if version < applied_version => version,
+                _ => continue,
+            };
+            match config.out_of_order {
+                Policy::Error => {
+                    return Err(MigrationError::OutOfOrder {
+                        migration: migration.name.clone(),
+                        version,
+                        applied: applied.name.clone(),
+                        applied_version,
+                    })
+                }
+                Policy::Warn => logging::warning(
+                    &migration.name,
+                    "is pending, but a migration with a higher version has been applied",
+                ),
+                Policy::Ignore => {}
+            }
         }
-
         Ok(())
     }
 
-    async fn perform(&self, db: &PgPool) -> Result<(), MigrationError> {
-        let mut tx = migration_try!(db.begin().await, "BEGIN TRANSACTION");
-        println!("Performing {}", self.name);
-        for statement in self.up.iter() {
-            migration_try!(sqlx::query(statement).execute(&mut tx).await, statement);
+    async fn undo<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        config: &MigratorConfig,
+        hooks: &Hooks,
+    ) -> Result<(), MigrationError> {
+        let (before, after) = self.session_statements::<DB>(config)?;
+        execute_all::<DB, _>(conn, &self.name, &before).await?;
+        let result = self.undo_transaction::<DB>(conn, config, hooks).await;
+        let after = execute_all::<DB, _>(conn, &self.name, &after).await;
+        result.and(after)
+    }
+
+    async fn undo_transaction<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        config: &MigratorConfig,
+        hooks: &Hooks,
+    ) -> Result<(), MigrationError> {
+        let started = Instant::now();
+        logging::migration_started(&self.name, Direction::Down);
+        if !self.single_transaction() {
+            execute_statements::<DB>(conn, self, Direction::Down, false, config, hooks).await?;
+            self.remove_record::<DB>(conn, config).await?;
+        } else {
+            let mut tx = migration_try!(
+                conn.begin().await,
+                Transaction {
+                    migration: Some(self.name.clone()),
+                    sql: String::from("BEGIN"),
+                }
+            );
+            execute_all::<DB, _>(
+                &mut tx,
+                &self.name,
+                &self.transaction_statements::<DB>(config)?,
+            )
+            .await?;
+            execute_statements::<DB>(&mut tx, self, Direction::Down, true, config, hooks).await?;
+            self.remove_record::<DB>(&mut tx, config).await?;
+            migration_try!(
+                tx.commit().await,
+                Transaction {
+                    migration: Some(self.name.clone()),
+                    sql: String::from("COMMIT"),
+                }
+            );
         }
-        migration_try!(
-            sqlx::query("INSERT INTO migrations (name) VALUES ($1)")
-                .bind(&self.name)
-                .execute(&mut tx)
-                .await,
-            "INSERT INTO migrations (name) VALUES ($1)"
-        );
-        migration_try!(tx.commit().await, "COMMIT TRANSACTION");
+        logging::migration_finished(&self.name, Direction::Down, started.elapsed());
         Ok(())
     }
 
-    async fn undo(&self, db: &PgPool) -> Result<(), MigrationError> {
-        let mut tx = migration_try!(db.begin().await, "BEGIN TRANSACTION");
-        println!("Undoing {}", self.name);
-        for statement in self.down.iter() {
-            migration_try!(sqlx::query(statement).execute(&mut tx).await, statement);
-        }
+    /// Removes the record of this migration being performed, along with the
+    /// records of any migrations it replaces
+    async fn remove_record<DB: MigrationBackend>(
+        &self,
+        conn: &mut DB::Connection,
+        config: &MigratorConfig,
+    ) -> Result<(), MigrationError> {
         // Only attempt to delete the migration record if we aren't the initial migration being undone.
         if self.name != migration_0_initial::NAME {
-            migration_try!(
-                sqlx::query("DELETE FROM migrations WHERE name = $1")
-                    .bind(&self.name)
-                    .execute(&mut tx)
-                    .await,
-                "DELETE FROM migrations WHERE name = $1"
-            );
+            let table = config.qualified_table_name();
+            for name in std::iter::once(&self.name).chain(self.replaces.iter()) {
+                let (schema, statement) = if is_builtin(name) {
+                    (None, DB::DELETE_MIGRATION)
+                } else {
+                    (
+                        Some(config.recorded_schema()),
+                        DB::DELETE_MIGRATION_WITH_SCHEMA,
+                    )
+                };
+                migration_try!(
+                    DB::remove_migration(conn, &table, schema, name).await,
+                    Bookkeeping {
+                        migration: Some(self.name.clone()),
+                        sql: render(statement, &table),
+                    }
+                );
+            }
         }
-        migration_try!(tx.commit().await, "COMMIT TRANSACTION");
         Ok(())
     }
 }
 
+/// Executes a migration's statements for `direction`, interleaved with its
+/// Rust code, notifying the observers as each step starts and finishes
+async fn execute_statements<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migration: &Migration,
+    direction: Direction,
+    in_transaction: bool,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    let down;
+    let (statements, functions, continue_on_error) = match direction {
+        Direction::Up => (
+            &migration.up[..],
+            &migration.up_fns[..],
+            migration.continue_on_error,
+        ),
+        Direction::Down => {
+            down = migration.down_statements(config);
+            (&down[..], &[][..], false)
+        }
+    };
+    let name = migration.name.as_str();
+    let setup = if migration.statement_transactions() {
+        Some(migration.transaction_statements::<DB>(config)?)
+    } else {
+        None
+    };
+    let total = statements.len() + functions.len();
+    let mut step = 0;
+    let mut functions = functions.iter().peekable();
+    for (index, statement) in statements.iter().enumerate() {
+        while let Some(function) = functions.next_if(|f| f.position == index) {
+            config.check_interrupted(name)?;
+            execute_function::<DB>(conn, name, function, step, total, hooks).await?;
+            step += 1;
+        }
+        config.check_interrupted(name)?;
+        hooks.statement_started(name, step, total);
+        let statement = config.substitute_vars(statement);
+        let started = Instant::now();
+        let result = if let Some(setup) = &setup {
+            execute_in_transaction::<DB>(conn, setup, &statement).await
+        } else if continue_on_error && in_transaction {
+            execute_in_savepoint::<DB>(conn, &statement).await
+        } else {
+            DB::execute(conn, &statement).await
+        };
+        match result {
+            Ok(()) => logging::statement_finished(name, index, started.elapsed()),
+            Err(err) if continue_on_error => logging::warning(
+                name,
+                &format!("continuing after statement {} failed: {}", index, err),
+            ),
+            Err(source) => {
+                return Err(MigrationError::Statement(Box::new(StatementError {
+                    migration: name.to_owned(),
+                    description: migration.description.clone(),
+                    index,
+                    count: statements.len(),
+                    sql: statement,
+                    source,
+                })))
+            }
+        }
+        hooks.statement_finished(name, step, total, started.elapsed());
+        step += 1;
+    }
+    for function in functions {
+        config.check_interrupted(name)?;
+        execute_function::<DB>(conn, name, function, step, total, hooks).await?;
+        step += 1;
+    }
+    Ok(())
+}
+
+/// Executes one of a migration's Rust steps, notifying the observers
+async fn execute_function<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    name: &str,
+    function: &MigrationFn,
+    step: usize,
+    total: usize,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    hooks.statement_started(name, step, total);
+    let started = Instant::now();
+    function.execute::<DB>(conn, name).await?;
+    hooks.statement_finished(name, step, total, started.elapsed());
+    Ok(())
+}
+
+/// Executes `statement` within a transaction of its own, after executing the
+/// `setup` statements in it
+async fn execute_in_transaction<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    setup: &[String],
+    statement: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = conn.begin().await?;
+    for setup in setup {
+        DB::execute(&mut tx, setup).await?;
+    }
+    DB::execute(&mut tx, statement).await?;
+    tx.commit().await
+}
+
+/// Executes `statement` within a savepoint of the current transaction,
+/// rolling the savepoint back if the statement fails
+async fn execute_in_savepoint<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    statement: &str,
+) -> Result<(), sqlx::Error> {
+    let mut savepoint = conn.begin().await?;
+    match DB::execute(&mut savepoint, statement).await {
+        Ok(()) => savepoint.commit().await,
+        Err(err) => {
+            savepoint.rollback().await?;
+            Err(err)
+        }
+    }
+}
+
+/// Prepends the crate's own migrations to the supplied migrations
+fn with_builtin_migrations<DB: MigrationBackend>(
+    config: &MigratorConfig,
+    mut supplied_migrations: Vec<Migration>,
+) -> Vec<Migration> {
+    let table = config.qualified_table_name();
+    let mut migrations = vec![
+        migration_0_initial::migration::<DB>(&table),
+        migration_1_checksums::migration::<DB>(&table),
+        migration_2_statistics::migration::<DB>(&table),
+        migration_3_applied_by::migration::<DB>(&table),
+        migration_4_build_id::migration::<DB>(&table),
+        migration_5_description::migration::<DB>(&table),
+        migration_6_schema::migration::<DB>(&table),
+        migration_7_batch::migration::<DB>(&table),
+        migration_8_repeatable::migration::<DB>(&table),
+    ];
+    // Repeatable migrations are executed after all of the others
+    supplied_migrations.sort_by_key(|m| m.repeatable);
+    migrations.append(&mut supplied_migrations);
+    migrations
+}
+
+fn is_builtin(name: &str) -> bool {
+    name == migration_0_initial::NAME
+        || name == migration_1_checksums::NAME
+        || name == migration_2_statistics::NAME
+        || name == migration_3_applied_by::NAME
+        || name == migration_4_build_id::NAME
+        || name == migration_5_description::NAME
+        || name == migration_6_schema::NAME
+        || name == migration_7_batch::NAME
+        || name == migration_8_repeatable::NAME
+}
+
+/// Returns whether the record of the migration `name` tracked for `schema`
+/// belongs to the configured history. The crate's own migrations are recorded
+/// without a schema, as every schema shares the table they maintain.
+pub(crate) fn tracked(config: &MigratorConfig, schema: &str, name: &str) -> bool {
+    schema == config.recorded_schema() || schema.is_empty() && is_builtin(name)
+}
+
+/// Applies `config.debug_modes` to the migrations in a debug mode
+fn apply_debug_modes(
+    migrations: &mut [Migration],
+    config: &MigratorConfig,
+) -> Result<(), MigrationError> {
+    for migration in migrations.iter_mut().filter(|m| m.mode != Mode::Stable) {
+        match config.debug_modes {
+            DebugModes::Execute => {}
+            DebugModes::Stable => {
+                logging::warning(
+                    &migration.name,
+                    "is in a debug mode, executing it as stable",
+                );
+                migration.mode = Mode::Stable;
+            }
+            DebugModes::Error => return Err(MigrationError::DebugMode(migration.name.clone())),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that no two migrations share a name, as only the first would be
+/// performed
+fn verify_unique(migrations: &[Migration]) -> Result<(), MigrationError> {
+    let mut names = HashSet::new();
+    let mut duplicates = Vec::new();
+    for migration in migrations {
+        if !names.insert(migration.name.as_str()) && !duplicates.contains(&migration.name) {
+            duplicates.push(migration.name.clone());
+        }
+    }
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        Err(MigrationError::DuplicateName(duplicates))
+    }
+}
+
+/// Checks that every supplied migration can be undone when
+/// `config.require_down` is set
+fn verify_downs(migrations: &[Migration], config: &MigratorConfig) -> Result<(), MigrationError> {
+    if !config.require_down {
+        return Ok(());
+    }
+    match migrations.iter().find(|m| {
+        !is_builtin(&m.name)
+            && (!m.up.is_empty() || !m.up_fns.is_empty())
+            && (m.down.is_empty() || m.irreversible)
+    }) {
+        Some(migration) => Err(MigrationError::MissingDown(migration.name.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Checks that every performed migration was supplied, such as when an older
+/// version of the code is deployed after a newer one
+fn verify_known(
+    migrations: &[Migration],
+    performed_migrations: &HashSet<String>,
+    config: &MigratorConfig,
+) -> Result<(), MigrationError> {
+    let mut supplied = migrations
+        .iter()
+        .map(|m| m.name.as_str())
+        .collect::<HashSet<_>>();
+    supplied.extend(snapshot::replaced(migrations));
+    let mut unknown = performed_migrations
+        .iter()
+        .filter(|name| !supplied.contains(name.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    unknown.sort();
+    match config.unknown_applied {
+        Policy::Error => return Err(MigrationError::UnknownApplied(unknown)),
+        Policy::Warn => {
+            for name in unknown.iter() {
+                logging::warning(name, "has been applied but wasn't supplied");
+            }
+        }
+        Policy::Ignore => {}
+    }
+    Ok(())
+}
+
+/// Returns the names of the performed migrations in the configured history
+async fn performed_migrations<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    config: &MigratorConfig,
+) -> Result<HashSet<String>, MigrationError> {
+    Ok(history::<DB>(conn, config)
+        .await?
+        .into_iter()
+        .filter(|(schema, name)| tracked(config, schema, name))
+        .map(|(_, name)| name)
+        .collect())
+}
+
+/// Returns whether the migrations table may record the history of another
+/// schema, in which case undoing every migration leaves the table in place
+pub(crate) async fn shared_table<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    config: &MigratorConfig,
+) -> Result<bool, MigrationError> {
+    if config.tracked_schema.is_some() {
+        return Ok(true);
+    }
+    Ok(history::<DB>(conn, config)
+        .await?
+        .iter()
+        .any(|(schema, _)| !schema.is_empty()))
+}
+
+/// Returns the schema and name of every performed migration. The query runs
+/// in its own transaction so that a missing `migrations` table doesn't abort
+/// a transaction the migrations are being executed within.
+async fn history<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    config: &MigratorConfig,
+) -> Result<Vec<(String, String)>, MigrationError> {
+    let table = config.qualified_table_name();
+    let mut tx = migration_try!(
+        conn.begin().await,
+        Transaction {
+            migration: None,
+            sql: String::from("BEGIN"),
+        }
+    );
+    let performed = read_history::<DB, _>(
+        DB::performed_migrations(&mut tx, &table).await,
+        render(DB::SELECT_MIGRATIONS, &table),
+        config,
+    )?;
+    migration_try!(
+        tx.rollback().await,
+        Transaction {
+            migration: None,
+            sql: String::from("ROLLBACK"),
+        }
+    );
+    Ok(performed)
+}
+
+/// Returns what was read from the migrations table by `sql`, or nothing if
+/// the table doesn't exist. Other failures are returned unless
+/// `config.strict_history` is disabled.
+pub(crate) fn read_history<DB: MigrationBackend, T: Default>(
+    result: Result<T, sqlx::Error>,
+    sql: String,
+    config: &MigratorConfig,
+) -> Result<T, MigrationError> {
+    match result {
+        Ok(history) => Ok(history),
+        Err(sqlx::Error::Database(err))
+            if err
+                .code()
+                .is_some_and(|code| DB::UNDEFINED_TABLE_ERROR_CODES.contains(&code.as_ref())) =>
+        {
+            Ok(T::default())
+        }
+        Err(_) if !config.strict_history => Ok(T::default()),
+        Err(source) => Err(MigrationError::Bookkeeping {
+            migration: None,
+            sql,
+            source,
+        }),
+    }
+}
+
+/// Executes each statement outside of a migration's transaction
+async fn execute_all<DB: MigrationBackend, S: AsRef<str>>(
+    conn: &mut DB::Connection,
+    name: &str,
+    statements: &[S],
+) -> Result<(), MigrationError> {
+    for statement in statements {
+        let statement = statement.as_ref();
+        migration_try!(
+            DB::execute(conn, statement).await,
+            Bookkeeping {
+                migration: Some(name.to_owned()),
+                sql: statement.to_owned(),
+            }
+        );
+    }
+    Ok(())
+}
+
 #[macro_export]
 /// Returns the last path component from std::file!()
 macro_rules! migration_name {
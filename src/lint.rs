@@ -0,0 +1,174 @@
+use super::{is_builtin, logging, plan::Step, Migration, MigrationError, MigratorConfig, Policy};
+
+#[derive(Debug, Clone)]
+/// An up statement that is likely to cause problems in production
+pub struct DangerousStatement {
+    pub migration: String,
+    /// The index of the statement within the migration's up statements
+    pub index: usize,
+    /// Why the statement is dangerous
+    pub reason: &'static str,
+}
+
+impl Migration {
+    /// Scans the up statements for known footguns, such as `CREATE INDEX`
+    /// without `CONCURRENTLY`
+    pub fn lint(&self) -> Vec<DangerousStatement> {
+        self.up
+            .iter()
+            .enumerate()
+            .filter_map(|(index, statement)| {
+                dangerous(statement).map(|reason| DangerousStatement {
+                    migration: self.name.clone(),
+                    index,
+                    reason,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Applies `config.dangerous_statements` to the migrations `steps` would
+/// perform, before any of them are executed
+pub(crate) fn verify_statements(
+    steps: &[Step],
+    config: &MigratorConfig,
+) -> Result<(), MigrationError> {
+    if config.dangerous_statements == Policy::Ignore {
+        return Ok(());
+    }
+    for step in steps {
+        let migration = match step {
+            Step::Perform(migration) if !is_builtin(&migration.name) => migration,
+            _ => continue,
+        };
+        for found in migration.lint() {
+            match config.dangerous_statements {
+                Policy::Error => {
+                    return Err(MigrationError::DangerousStatement {
+                        migration: found.migration,
+                        index: found.index,
+                        reason: found.reason,
+                    })
+                }
+                Policy::Warn => logging::warning(
+                    &found.migration,
+                    &format!("statement {} is dangerous: {}", found.index, found.reason),
+                ),
+                Policy::Ignore => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns why `statement` is dangerous, if it is
+fn dangerous(statement: &str) -> Option<&'static str> {
+    let words = statement
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c| c == ';' || c == ',').to_uppercase())
+        .collect::<Vec<_>>();
+    let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+    let followed_by = |sequence: &[&str], next: &str| {
+        words
+            .windows(sequence.len())
+            .enumerate()
+            .any(|(i, window)| window == sequence && words.get(i + sequence.len()) != Some(&next))
+    };
+
+    if followed_by(&["DROP", "TABLE"], "IF") {
+        Some("DROP TABLE without IF EXISTS fails if the table has already been dropped")
+    } else if words.starts_with(&["ALTER", "TABLE"])
+        && words.windows(3).any(|w| w == ["SET", "NOT", "NULL"])
+    {
+        Some("SET NOT NULL scans the whole table while holding an exclusive lock")
+    } else if followed_by(&["CREATE", "INDEX"], "CONCURRENTLY")
+        || followed_by(&["CREATE", "UNIQUE", "INDEX"], "CONCURRENTLY")
+    {
+        Some("CREATE INDEX without CONCURRENTLY blocks writes to the table until it finishes")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration_0_initial;
+
+    #[test]
+    fn flags_dangerous_statements() {
+        for statement in [
+            "DROP TABLE users",
+            "CREATE INDEX users_email ON users (email)",
+            "create unique index users_email on users (email);",
+            "ALTER TABLE users ALTER COLUMN email SET NOT NULL",
+        ] {
+            assert!(dangerous(statement).is_some(), "{}", statement);
+        }
+    }
+
+    #[test]
+    fn accepts_safe_statements() {
+        for statement in [
+            "DROP TABLE IF EXISTS users",
+            "CREATE INDEX CONCURRENTLY users_email ON users (email)",
+            "CREATE UNIQUE INDEX CONCURRENTLY users_email ON users (email)",
+            "ALTER TABLE users ADD COLUMN email TEXT NOT NULL DEFAULT ''",
+            "CREATE TABLE users (id BIGINT NOT NULL)",
+        ] {
+            assert_eq!(dangerous(statement), None, "{}", statement);
+        }
+    }
+
+    #[test]
+    fn reports_the_index_of_each_dangerous_statement() {
+        let found = Migration::new("users")
+            .with_up("CREATE TABLE users (id BIGINT)")
+            .with_up("CREATE INDEX users_id ON users (id)")
+            .with_up("DROP TABLE IF EXISTS old_users")
+            .with_up("DROP TABLE accounts")
+            .lint();
+        let found = found
+            .iter()
+            .map(|f| (f.migration.as_str(), f.index))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![("users", 1), ("users", 3)]);
+    }
+
+    #[test]
+    fn applies_the_policy_to_performed_migrations() {
+        let steps = vec![Step::Perform(
+            Migration::new("users").with_up("DROP TABLE users"),
+        )];
+        let error = MigratorConfig::default().dangerous_statements(Policy::Error);
+        match verify_statements(&steps, &error) {
+            Err(MigrationError::DangerousStatement {
+                migration, index, ..
+            }) => assert_eq!((migration.as_str(), index), ("users", 0)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let warn = MigratorConfig::default().dangerous_statements(Policy::Warn);
+        assert!(verify_statements(&steps, &warn).is_ok());
+        // Only the statements of migrations being performed are checked
+        let undo = vec![Step::Undo(
+            Migration::new("users").with_up("DROP TABLE users"),
+        )];
+        assert!(verify_statements(&undo, &error).is_ok());
+    }
+
+    #[test]
+    fn skips_builtin_migrations_and_ignored_statements() {
+        let builtin = vec![Step::Perform(
+            Migration::new(migration_0_initial::NAME).with_up("DROP TABLE users"),
+        )];
+        let error = MigratorConfig::default().dangerous_statements(Policy::Error);
+        assert!(verify_statements(&builtin, &error).is_ok());
+
+        let steps = vec![Step::Perform(
+            Migration::new("users").with_up("DROP TABLE users"),
+        )];
+        let ignore = MigratorConfig::default().dangerous_statements(Policy::Ignore);
+        assert!(verify_statements(&steps, &ignore).is_ok());
+    }
+}
@@ -0,0 +1,139 @@
+//! Progress reporting. Events are emitted through `tracing` when the feature
+//! is enabled, and through `log` otherwise.
+
+use super::{Direction, RepairedChecksum};
+use std::time::Duration;
+
+fn action(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "Performing",
+        Direction::Down => "Undoing",
+    }
+}
+
+pub(crate) fn migration_started(name: &str, direction: Direction) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(migration = name, "{} {}", action(direction), name);
+    #[cfg(not(feature = "tracing"))]
+    log::info!("{} {}", action(direction), name);
+}
+
+pub(crate) fn statement_finished(name: &str, index: usize, elapsed: Duration) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        migration = name,
+        statement = index,
+        duration_ms = elapsed.as_millis() as u64,
+        "Executed statement {} of {}",
+        index,
+        name
+    );
+    #[cfg(not(feature = "tracing"))]
+    log::debug!("Executed statement {} of {} in {:?}", index, name, elapsed);
+}
+
+pub(crate) fn migration_finished(name: &str, direction: Direction, elapsed: Duration) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        migration = name,
+        duration_ms = elapsed.as_millis() as u64,
+        "Finished {} {}",
+        action(direction).to_lowercase(),
+        name
+    );
+    #[cfg(not(feature = "tracing"))]
+    log::info!(
+        "Finished {} {} in {:?}",
+        action(direction).to_lowercase(),
+        name,
+        elapsed
+    );
+}
+
+pub(crate) fn migration_skipped(name: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        migration = name,
+        "Skipped {}, its condition wasn't met",
+        name
+    );
+    #[cfg(not(feature = "tracing"))]
+    log::info!("Skipped {}, its condition wasn't met", name);
+}
+
+pub(crate) fn snapshot_adopted(name: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        migration = name,
+        "Recorded {}, the migrations it replaces have been applied",
+        name
+    );
+    #[cfg(not(feature = "tracing"))]
+    log::info!(
+        "Recorded {}, the migrations it replaces have been applied",
+        name
+    );
+}
+
+pub(crate) fn migration_renamed(legacy: &str, name: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        migration = name,
+        "Renamed the record of {} to {}",
+        legacy,
+        name
+    );
+    #[cfg(not(feature = "tracing"))]
+    log::info!("Renamed the record of {} to {}", legacy, name);
+}
+
+pub(crate) fn checksum_repaired(checksum: &RepairedChecksum) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        migration = checksum.name.as_str(),
+        "Repaired the checksum of {}",
+        checksum
+    );
+    #[cfg(not(feature = "tracing"))]
+    log::info!("Repaired the checksum of {}", checksum);
+}
+
+pub(crate) fn waiting_for_leader() {
+    #[cfg(feature = "tracing")]
+    tracing::info!("Waiting for another process to apply the migrations");
+    #[cfg(not(feature = "tracing"))]
+    log::info!("Waiting for another process to apply the migrations");
+}
+
+pub(crate) fn backfill_progress(table: &str, batches: u64, last_key: i64) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        table,
+        batches,
+        last_key,
+        "Backfilled {} batches of {}",
+        batches,
+        table
+    );
+    #[cfg(not(feature = "tracing"))]
+    log::info!(
+        "Backfilled {} batches of {}, through key {}",
+        batches,
+        table,
+        last_key
+    );
+}
+
+pub(crate) fn backfill_resumed(table: &str, last_key: i64) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(table, last_key, "Resuming the backfill of {}", table);
+    #[cfg(not(feature = "tracing"))]
+    log::info!("Resuming the backfill of {} after key {}", table, last_key);
+}
+
+pub(crate) fn warning(name: &str, message: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(migration = name, "{}: {}", name, message);
+    #[cfg(not(feature = "tracing"))]
+    log::warn!("{}: {}", name, message);
+}
@@ -0,0 +1,178 @@
+use super::{is_builtin, logging, Migration, MigrationBackend, MigratorConfig};
+
+impl Migration {
+    /// Declare that the migration changes `table`, besides the tables named
+    /// by its up statements, such as a table updated by Rust code. The
+    /// maintenance statements configured for touched tables are executed
+    /// against it after the migration commits.
+    pub fn touches(mut self, table: &str) -> Self {
+        self.touches.push(table.to_owned());
+        self
+    }
+
+    /// Returns the tables the up statements create, alter or write to under
+    /// their final names, followed by the tables declared with `touches`
+    pub(crate) fn touched_tables(&self, config: &MigratorConfig) -> Vec<String> {
+        let mut tables = Vec::<String>::new();
+        for statement in self.up.iter() {
+            for command in config.substitute_vars(statement).split(';') {
+                if let Some((table, new_name)) = renamed_table(command) {
+                    tables.retain(|touched| *touched != table);
+                    tables.push(new_name);
+                } else if let Some(table) = touched_table(command) {
+                    tables.push(table);
+                }
+            }
+        }
+        tables.extend(self.touches.iter().cloned());
+        let mut unique = Vec::<String>::new();
+        for table in tables {
+            if !unique.contains(&table) {
+                unique.push(table);
+            }
+        }
+        unique
+    }
+}
+
+/// Executes the configured maintenance statements, such as `ANALYZE`, against
+/// each table `migration` touched, once it has been committed. Failures are
+/// only logged, as the migration itself was performed.
+pub(crate) async fn run<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migration: &Migration,
+    config: &MigratorConfig,
+) {
+    if is_builtin(&migration.name) {
+        return;
+    }
+    let statements = config
+        .analyze_touched_tables
+        .then_some(DB::ANALYZE_TABLE)
+        .into_iter()
+        .chain(config.touched_table_statements.iter().map(String::as_str))
+        .collect::<Vec<_>>();
+    if statements.is_empty() {
+        return;
+    }
+    for table in migration.touched_tables(config) {
+        for statement in statements.iter() {
+            let statement = statement.replace("{table}", &table);
+            if let Err(err) = DB::execute(conn, &statement).await {
+                logging::warning(
+                    &migration.name,
+                    &format!("maintenance statement {:?} failed: {}", statement, err),
+                );
+            }
+        }
+    }
+}
+
+/// Returns the table a single sql command creates, alters or writes to, if
+/// it's a `CREATE TABLE`, `ALTER TABLE`, `CREATE INDEX`, `INSERT`, `UPDATE`
+/// or `DELETE` command
+fn touched_table(command: &str) -> Option<String> {
+    let words = command.split_whitespace().collect::<Vec<_>>();
+    let upper = words
+        .iter()
+        .map(|word| word.to_uppercase())
+        .collect::<Vec<_>>();
+    let upper = upper.iter().map(String::as_str).collect::<Vec<_>>();
+    let skip = |mut index: usize, optional: &[&[&str]]| {
+        for keywords in optional {
+            if upper[index..].starts_with(keywords) {
+                index += keywords.len();
+            }
+        }
+        index
+    };
+    let index = match upper.as_slice() {
+        ["CREATE", "TABLE", ..] => skip(2, &[&["IF", "NOT", "EXISTS"]]),
+        ["CREATE", "UNLOGGED", "TABLE", ..] => skip(3, &[&["IF", "NOT", "EXISTS"]]),
+        ["ALTER", "TABLE", ..] => skip(2, &[&["IF", "EXISTS"], &["ONLY"]]),
+        ["CREATE", "INDEX", ..] | ["CREATE", "UNIQUE", "INDEX", ..] => {
+            let on = upper.iter().position(|word| *word == "ON")?;
+            skip(on + 1, &[&["ONLY"]])
+        }
+        ["INSERT", "INTO", ..] => 2,
+        ["UPDATE", ..] => skip(1, &[&["ONLY"]]),
+        ["DELETE", "FROM", ..] => skip(2, &[&["ONLY"]]),
+        _ => return None,
+    };
+    name(words.get(index)?)
+}
+
+/// Returns the current and new names of the table renamed by an `ALTER TABLE
+/// name RENAME TO new_name` command
+fn renamed_table(command: &str) -> Option<(String, String)> {
+    let words = command.split_whitespace().collect::<Vec<_>>();
+    match words.as_slice() {
+        [alter, table, name_word, rename, to, new_name, ..]
+            if alter.eq_ignore_ascii_case("ALTER")
+                && table.eq_ignore_ascii_case("TABLE")
+                && rename.eq_ignore_ascii_case("RENAME")
+                && to.eq_ignore_ascii_case("TO") =>
+        {
+            Some((name(name_word)?, name(new_name)?))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the table name within `word`, which may be immediately followed
+/// by an opening parenthesis
+fn name(word: &str) -> Option<String> {
+    let table = word.split('(').next()?.trim_end_matches(',');
+    (!table.is_empty()).then(|| table.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touched(migration: Migration) -> Vec<String> {
+        migration.touched_tables(&MigratorConfig::default())
+    }
+
+    #[test]
+    fn finds_tables_written_by_up_statements() {
+        let migration = Migration::new("touched")
+            .with_up("CREATE TABLE IF NOT EXISTS users(id BIGINT)")
+            .with_up("ALTER TABLE ONLY invoices ADD COLUMN total INT")
+            .with_up("CREATE UNIQUE INDEX users_email ON users (email)")
+            .with_up("INSERT INTO audit (id) VALUES (1); UPDATE ONLY accounts SET a = 1")
+            .with_up("DELETE FROM sessions")
+            .with_up("SELECT * FROM ignored");
+        assert_eq!(
+            touched(migration),
+            vec!["users", "invoices", "audit", "accounts", "sessions"]
+        );
+    }
+
+    #[test]
+    fn reports_renamed_tables_under_their_new_names() {
+        let migration = Migration::new("renamed")
+            .with_up("CREATE TABLE users (id BIGINT)")
+            .with_up("ALTER TABLE users RENAME TO accounts");
+        assert_eq!(touched(migration), vec!["accounts"]);
+    }
+
+    #[test]
+    fn includes_declared_tables_once() {
+        let migration = Migration::new("declared")
+            .with_up("UPDATE users SET active = true")
+            .touches("users")
+            .touches("events");
+        assert_eq!(touched(migration), vec!["users", "events"]);
+    }
+
+    #[test]
+    fn substitutes_template_variables() {
+        let mut config = MigratorConfig::default();
+        config
+            .vars
+            .insert(String::from("prefix"), String::from("staging_"));
+        let migration = Migration::new("vars").with_up("CREATE TABLE {{prefix}}users (id BIGINT)");
+        assert_eq!(migration.touched_tables(&config), vec!["staging_users"]);
+    }
+}
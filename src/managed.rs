@@ -0,0 +1,24 @@
+use super::Migration;
+
+impl Migration {
+    /// Create a migration named `name` that keeps a function, procedure or
+    /// trigger defined by `definition`, such as a `CREATE OR REPLACE FUNCTION`
+    /// statement. Unlike other migrations, changing the definition doesn't
+    /// cause a checksum mismatch: the definition is executed again the next
+    /// time migrations are run, and its new checksum is recorded.
+    pub fn managed_function(name: &str, definition: &str) -> Self {
+        let mut migration = Migration::new(name).with_up(definition);
+        migration.managed = true;
+        migration
+    }
+
+    /// Create a repeatable migration named `name`, such as for views and
+    /// grants, like Flyway's `R__` migrations. Repeatable migrations are
+    /// executed after all of the other migrations, and like a managed
+    /// function, executed again whenever `sql` changes.
+    pub fn repeatable(name: &str, sql: &str) -> Self {
+        let mut migration = Migration::managed_function(name, sql);
+        migration.repeatable = true;
+        migration
+    }
+}
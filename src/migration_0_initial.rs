@@ -1,20 +1,9 @@
-use super::Migration;
+use super::{render, Migration, MigrationBackend};
 
 pub const NAME: &str = "initial";
 
-pub fn migration() -> Migration {
+pub fn migration<DB: MigrationBackend>(table: &str) -> Migration {
     Migration::new(NAME)
-        .with_up(
-            r#"
-        CREATE TABLE migrations (
-            name TEXT NOT NULL PRIMARY KEY,
-            executed_at TIMESTAMPTZ NOT NULL DEFAULT now()
-        )
-        "#,
-        )
-        .with_down(
-            r#"
-        DROP TABLE IF EXISTS migrations
-        "#,
-        )
+        .with_up(&render(DB::CREATE_MIGRATIONS_TABLE, table))
+        .with_down(&render(DB::DROP_MIGRATIONS_TABLE, table))
 }
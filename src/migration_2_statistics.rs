@@ -0,0 +1,9 @@
+use super::{render, Migration, MigrationBackend};
+
+pub const NAME: &str = "statistics";
+
+pub fn migration<DB: MigrationBackend>(table: &str) -> Migration {
+    Migration::new(NAME)
+        .with_up(&render(DB::ADD_STATISTICS_COLUMNS, table))
+        .with_down(&render(DB::DROP_STATISTICS_COLUMNS, table))
+}
@@ -0,0 +1,9 @@
+use super::{render, Migration, MigrationBackend};
+
+pub const NAME: &str = "applied_by";
+
+pub fn migration<DB: MigrationBackend>(table: &str) -> Migration {
+    Migration::new(NAME)
+        .with_up(&render(DB::ADD_APPLIED_BY_COLUMNS, table))
+        .with_down(&render(DB::DROP_APPLIED_BY_COLUMNS, table))
+}
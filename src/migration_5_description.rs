@@ -0,0 +1,9 @@
+use super::{render, Migration, MigrationBackend};
+
+pub const NAME: &str = "description";
+
+pub fn migration<DB: MigrationBackend>(table: &str) -> Migration {
+    Migration::new(NAME)
+        .with_up(&render(DB::ADD_DESCRIPTION_COLUMN, table))
+        .with_down(&render(DB::DROP_DESCRIPTION_COLUMN, table))
+}
@@ -0,0 +1,1600 @@
+use super::{
+    apply_debug_modes, dependencies, drift, environment, extension, file_name, hooks::Hooks,
+    import, is_builtin, lint, logging, migration_7_batch, performed_migrations, plan, plan::Step,
+    render, repair, shared_table, snapshot, source, status, tags, verify_downs, verify_known,
+    verify_unique, with_builtin_migrations, DebugModes, Direction, Environment, ImportedMigration,
+    MetricsSink, Migration, MigrationBackend, MigrationError, MigrationObserver, MigrationReport,
+    MigrationSource, MigrationStatus, MigratorConfig, PlannedMigration, Policy, RepairedChecksum,
+    ReportedMigration, SchemaReport, TagFilter,
+};
+use futures_core::future::BoxFuture;
+use sqlx::{Acquire, Connection};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::watch;
+
+/// The longest wait before retrying a migration that failed because of a
+/// transient error
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Executes a set of migrations against a pool, connection, or transaction,
+/// using the configured options.
+pub struct Migrator<A> {
+    executor: A,
+    migrations: Vec<Migration>,
+    config: MigratorConfig,
+    hooks: Hooks,
+}
+
+impl<A> Migrator<A> {
+    /// Create a migrator that executes migrations using `executor`. When
+    /// given a transaction, each migration is executed within a savepoint.
+    pub fn new(executor: A) -> Self {
+        Migrator {
+            executor,
+            migrations: Vec::new(),
+            config: MigratorConfig::default(),
+            hooks: Hooks::default(),
+        }
+    }
+
+    /// Add migrations to execute after any previously added migrations.
+    /// Migrations with a version are executed in order of their versions,
+    /// before any migrations without a version. Migrations are then moved
+    /// after the migrations they depend on.
+    pub fn with_migrations(mut self, mut migrations: Vec<Migration>) -> Self {
+        self.migrations.append(&mut migrations);
+        self.migrations
+            .sort_by_key(|m| m.version.unwrap_or(u64::MAX));
+        dependencies::sort(&mut self.migrations);
+        self
+    }
+
+    /// Add the migrations loaded from `source` to execute after any
+    /// previously added migrations, like `with_migrations`
+    pub async fn with_source<S: MigrationSource + ?Sized>(
+        self,
+        source: &S,
+    ) -> Result<Self, MigrationError> {
+        let migrations = source.migrations().await.map_err(MigrationError::Source)?;
+        Ok(self.with_migrations(migrations))
+    }
+
+    /// Replace all of the options with `config`
+    pub fn with_config(mut self, config: MigratorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set the name of the table that records which migrations have been
+    /// performed
+    pub fn table_name(mut self, table_name: &str) -> Self {
+        self.config = self.config.table_name(table_name);
+        self
+    }
+
+    /// Set the schema containing the migrations table
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.config = self.config.schema(schema);
+        self
+    }
+
+    /// Set what to do when an applied migration's up statements have changed
+    pub fn checksum_mismatch(mut self, policy: Policy) -> Self {
+        self.config = self.config.checksum_mismatch(policy);
+        self
+    }
+
+    /// Undo and perform again the applied migrations that have changed, and
+    /// the migrations depending on them, when debug modes are executed
+    pub fn redo_changed(mut self, redo_changed: bool) -> Self {
+        self.config = self.config.redo_changed(redo_changed);
+        self
+    }
+
+    /// Roll back the applied migrations from the migration named `name`
+    /// onward, then perform them again, when debug modes are executed
+    pub fn nuclear_debug_from(mut self, name: &str) -> Self {
+        self.config = self.config.nuclear_debug_from(name);
+        self
+    }
+
+    /// Set what to do when a pending migration has a lower version than an
+    /// applied migration
+    pub fn out_of_order(mut self, policy: Policy) -> Self {
+        self.config = self.config.out_of_order(policy);
+        self
+    }
+
+    /// Set what to do when migrations have been applied that weren't
+    /// supplied
+    pub fn unknown_applied(mut self, policy: Policy) -> Self {
+        self.config = self.config.unknown_applied(policy);
+        self
+    }
+
+    /// Hold an advisory lock using `key` while migrations run
+    pub fn lock_key(mut self, key: i64) -> Self {
+        self.config = self.config.lock_key(key);
+        self
+    }
+
+    /// Fail a migration when one of its statements runs for longer than
+    /// `timeout`
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.config = self.config.statement_timeout(timeout);
+        self
+    }
+
+    /// Fail a migration when one of its statements waits for longer than
+    /// `timeout` to acquire a lock
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.config = self.config.lock_timeout(timeout);
+        self
+    }
+
+    /// Retry a migration up to `retries` times when it fails because of a
+    /// transient error, waiting `backoff` before the first retry
+    pub fn retry(mut self, retries: u32, backoff: Duration) -> Self {
+        self.config = self.config.retry(retries, backoff);
+        self
+    }
+
+    /// Set what to do with migrations in a debug mode
+    pub fn debug_modes(mut self, debug_modes: DebugModes) -> Self {
+        self.config = self.config.debug_modes(debug_modes);
+        self
+    }
+
+    /// Set whether undoing migrations returns an error instead
+    pub fn forbid_down(mut self, forbid: bool) -> Self {
+        self.config = self.config.forbid_down(forbid);
+        self
+    }
+
+    /// Set whether every migration with up statements must have down
+    /// statements
+    pub fn require_down(mut self, require: bool) -> Self {
+        self.config = self.config.require_down(require);
+        self
+    }
+
+    /// Set whether undoing a migration drops the extensions it requires
+    pub fn drop_extensions(mut self, drop: bool) -> Self {
+        self.config = self.config.drop_extensions(drop);
+        self
+    }
+
+    /// Execute `statement` for every table, view or sequence a migration
+    /// creates, with `{object}` and `{kind}` replaced
+    pub fn for_new_objects(mut self, statement: &str) -> Self {
+        self.config = self.config.for_new_objects(statement);
+        self
+    }
+
+    /// Set whether `ANALYZE` is executed on the tables each migration touched
+    /// once it commits
+    pub fn analyze_touched_tables(mut self, analyze: bool) -> Self {
+        self.config = self.config.analyze_touched_tables(analyze);
+        self
+    }
+
+    /// Execute `statement` on each table a migration touched once it
+    /// commits, with `{table}` replaced
+    pub fn for_touched_tables(mut self, statement: &str) -> Self {
+        self.config = self.config.for_touched_tables(statement);
+        self
+    }
+
+    /// Substitute each value of `vars` for the `{{name}}` placeholders named
+    /// by its key in migration statements
+    pub fn with_vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.config = self.config.with_vars(vars);
+        self
+    }
+
+    /// Set what to do when a pending migration contains a statement that is
+    /// likely to cause problems in production
+    pub fn dangerous_statements(mut self, policy: Policy) -> Self {
+        self.config = self.config.dangerous_statements(policy);
+        self
+    }
+
+    /// Only perform the pending migrations selected by `filter`
+    pub fn tag_filter(mut self, filter: TagFilter) -> Self {
+        self.config = self.config.tag_filter(filter);
+        self
+    }
+
+    /// Set the environment selecting the migrations marked with `only_in` or
+    /// `skip_in`
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.config = self.config.environment(environment);
+        self
+    }
+
+    /// Notify listeners on `channel` after a run applies migrations
+    pub fn notify_channel(mut self, channel: &str) -> Self {
+        self.config = self.config.notify_channel(channel);
+        self
+    }
+
+    /// Execute the migrations only when this process acquires the lock, and
+    /// otherwise wait for them to be applied
+    pub fn leader_election(mut self, timeout: Duration, poll_interval: Duration) -> Self {
+        self.config = self.config.leader_election(timeout, poll_interval);
+        self
+    }
+
+    /// Set whether failing to read the migrations table returns an error
+    /// unless the table doesn't exist
+    pub fn strict_history(mut self, strict: bool) -> Self {
+        self.config = self.config.strict_history(strict);
+        self
+    }
+
+    /// Set the directory relative paths given to `with_up_file` and
+    /// `with_down_file` are resolved against
+    pub fn migrations_root<P: AsRef<Path>>(mut self, root: P) -> Self {
+        self.config = self.config.migrations_root(root);
+        self
+    }
+
+    /// Set the search path of the connection while each migration runs
+    pub fn search_path(mut self, search_path: &str) -> Self {
+        self.config = self.config.search_path(search_path);
+        self
+    }
+
+    /// Set the schema recorded with each migration, keeping a separate
+    /// history for the migrations of `schema`
+    pub fn tracked_schema(mut self, schema: &str) -> Self {
+        self.config = self.config.tracked_schema(schema);
+        self
+    }
+
+    /// Stop the run at the next statement boundary once `cancellation` holds
+    /// true, rolling back the migration in progress
+    pub fn cancel_on(mut self, cancellation: watch::Receiver<bool>) -> Self {
+        self.config = self.config.cancel_on(cancellation);
+        self
+    }
+
+    /// Stop the run at the next statement boundary once `deadline` passes,
+    /// rolling back the migration in progress
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.config = self.config.deadline(deadline);
+        self
+    }
+
+    /// Set the host name recorded for each migration performed
+    pub fn hostname(mut self, hostname: &str) -> Self {
+        self.config = self.config.hostname(hostname);
+        self
+    }
+
+    /// Set the application version recorded for each migration performed
+    pub fn app_version(mut self, version: &str) -> Self {
+        self.config = self.config.app_version(version);
+        self
+    }
+
+    /// Set the identifier of the build recorded for each migration performed
+    pub fn build_id(mut self, build_id: &str) -> Self {
+        self.config = self.config.build_id(build_id);
+        self
+    }
+
+    /// Call `hook` with the name and direction of each migration before it is
+    /// performed or undone
+    pub fn on_before_migration<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&str, Direction) + Send + Sync + 'static,
+    {
+        self.hooks.before_migration.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` with the name, direction and outcome of each migration
+    /// after it is performed or undone
+    pub fn on_after_migration<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&str, Direction, Result<(), &MigrationError>) + Send + Sync + 'static,
+    {
+        self.hooks.after_migration.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` before running, undoing or rolling back migrations. When a
+    /// lock key is set, the hook is called once the lock is held.
+    pub fn on_before_all<F>(mut self, hook: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.hooks.before_all.push(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` with the outcome after running, undoing or rolling back
+    /// migrations
+    pub fn on_after_all<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(Result<(), &MigrationError>) + Send + Sync + 'static,
+    {
+        self.hooks.after_all.push(Box::new(hook));
+        self
+    }
+
+    /// Notify `observer` as each statement of a migration starts and
+    /// finishes executing
+    pub fn with_observer<O: MigrationObserver + 'static>(mut self, observer: O) -> Self {
+        self.hooks.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Report counters and histograms for each migration executed to `sink`
+    pub fn with_metrics<M: MetricsSink + 'static>(mut self, sink: M) -> Self {
+        self.hooks.metrics.push(Box::new(sink));
+        self
+    }
+
+    /// Await `confirm` with the plan of each migration before it is undone,
+    /// or performed when it's tagged `destructive`, such as to prompt on the
+    /// command line. The migration is only executed if `confirm` returns
+    /// true, and `MigrationError::NotConfirmed` is returned otherwise.
+    pub fn confirm_with<F>(mut self, confirm: F) -> Self
+    where
+        F: Fn(PlannedMigration) -> BoxFuture<'static, bool> + Send + Sync + 'static,
+    {
+        self.hooks.confirm = Some(Box::new(confirm));
+        self
+    }
+}
+
+impl<'a, A> Migrator<A>
+where
+    A: Acquire<'a>,
+    A::Database: MigrationBackend,
+{
+    /// Reads the files added by `with_up_file` and `with_down_file` into the
+    /// migrations' statements
+    fn load_files(mut self) -> Result<Self, MigrationError> {
+        source::load_files(&mut self.migrations, &self.config)?;
+        Ok(self)
+    }
+
+    /// Execute all of the pending migrations, returning what was done to each
+    /// migration
+    pub async fn run(self) -> Result<MigrationReport, MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        if !lead_or_follow::<A::Database>(&mut conn, &migrations, &config).await? {
+            return Ok(MigrationReport::default());
+        }
+        hooks.before_all();
+        let result =
+            execute_plan::<A::Database>(&mut conn, migrations, None, &config, &hooks).await;
+        hooks.after_all(&result);
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Execute the pending pre-deploy migrations, the backwards-compatible
+    /// changes the running release tolerates, before a new release is
+    /// deployed. Migrations assigned to the post-deploy phase with
+    /// `Migration::post_deploy` are left pending.
+    pub async fn run_pre_deploy(mut self) -> Result<MigrationReport, MigrationError> {
+        self.config
+            .tag_filter
+            .exclude
+            .push(String::from(tags::POST_DEPLOY));
+        self.run().await
+    }
+
+    /// Execute all of the pending migrations, including the post-deploy
+    /// migrations, such as cleanup once no running release uses the old
+    /// schema. Pending pre-deploy migrations are executed too.
+    pub async fn run_post_deploy(self) -> Result<MigrationReport, MigrationError> {
+        self.run().await
+    }
+
+    /// Execute all of the pending migrations against each of `schemas`, such
+    /// as one schema per tenant. Each schema is made the connection's search
+    /// path while its migrations run. The schemas share one migrations table,
+    /// which records the schema of each migration so that each schema's
+    /// history is tracked separately, as with `run_schema_sets`. A failure in
+    /// one schema doesn't prevent the remaining schemas from being migrated.
+    pub async fn run_for_schemas(
+        self,
+        schemas: &[&str],
+    ) -> Result<Vec<SchemaReport>, MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            mut config,
+            hooks,
+        } = self.load_files()?;
+        let (set_search_path, reset_search_path) =
+            match (A::Database::SET_SEARCH_PATH, A::Database::RESET_SEARCH_PATH) {
+                (Some(set), Some(reset)) => (set, reset),
+                _ => return Err(MigrationError::Unsupported("Migrating multiple schemas")),
+            };
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        if let (None, Some(statement)) = (&config.schema, A::Database::SELECT_CURRENT_SCHEMA) {
+            // The table stays where it is while each schema is the search path
+            let current = migration_try!(
+                A::Database::query_schema(&mut conn, statement, &[]).await,
+                Bookkeeping {
+                    migration: None,
+                    sql: statement.to_owned(),
+                }
+            );
+            config.schema = current;
+        }
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        let mut reports = Vec::with_capacity(schemas.len());
+        for &schema in schemas {
+            let config = MigratorConfig {
+                search_path: None,
+                ..config.clone().tracked_schema(schema)
+            };
+            let set_search_path = set_search_path.replace("{schema}", schema);
+            hooks.before_all();
+            let result = match A::Database::execute(&mut conn, &set_search_path).await {
+                Ok(()) => {
+                    execute_plan::<A::Database>(
+                        &mut conn,
+                        migrations.clone(),
+                        None,
+                        &config,
+                        &hooks,
+                    )
+                    .await
+                }
+                Err(source) => Err(MigrationError::Bookkeeping {
+                    migration: None,
+                    sql: set_search_path,
+                    source,
+                }),
+            };
+            hooks.after_all(&result);
+            reports.push(SchemaReport {
+                schema: schema.to_owned(),
+                result,
+            });
+        }
+        let result = match A::Database::execute(&mut conn, reset_search_path).await {
+            Ok(()) => Ok(reports),
+            Err(source) => Err(MigrationError::Bookkeeping {
+                migration: None,
+                sql: reset_search_path.to_owned(),
+                source,
+            }),
+        };
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Execute each set of migrations against its own schema, such as one
+    /// schema per domain of a modular monolith. The sets share one migrations
+    /// table, which records the schema of each migration so that each set's
+    /// history is tracked separately, as with `tracked_schema`. Where
+    /// supported, each schema is made the search path while its migrations
+    /// run, and the schemas must already exist. The migrations given to
+    /// `with_migrations` aren't executed. A failure in one schema doesn't
+    /// prevent the remaining schemas from being migrated.
+    pub async fn run_schema_sets(
+        self,
+        sets: Vec<(&str, Vec<Migration>)>,
+    ) -> Result<Vec<SchemaReport>, MigrationError> {
+        let Migrator {
+            executor,
+            mut config,
+            hooks,
+            ..
+        } = self;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        if let (None, Some(statement)) = (&config.schema, A::Database::SELECT_CURRENT_SCHEMA) {
+            // The table stays where it is while each schema is the search path
+            let current = migration_try!(
+                A::Database::query_schema(&mut conn, statement, &[]).await,
+                Bookkeeping {
+                    migration: None,
+                    sql: statement.to_owned(),
+                }
+            );
+            config.schema = current;
+        }
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        let mut reports = Vec::with_capacity(sets.len());
+        for (schema, mut migrations) in sets {
+            let mut config = config.clone().tracked_schema(schema);
+            if A::Database::SET_LOCAL_SEARCH_PATH.is_some() {
+                config = config.search_path(schema);
+            }
+            hooks.before_all();
+            let result = match source::load_files(&mut migrations, &config) {
+                Ok(()) => {
+                    execute_plan::<A::Database>(&mut conn, migrations, None, &config, &hooks).await
+                }
+                Err(err) => Err(err),
+            };
+            hooks.after_all(&result);
+            reports.push(SchemaReport {
+                schema: schema.to_owned(),
+                result,
+            });
+        }
+        release_lock::<A::Database, _>(&mut conn, &config, Ok(reports)).await
+    }
+
+    /// Replay all of the migrations against a throwaway schema: every up is
+    /// applied, every down is undone in reverse, then every up is applied
+    /// again. A failure while reapplying usually means the previous down left
+    /// something behind. The schema is dropped afterwards, and the database's
+    /// own migrations table isn't touched.
+    pub async fn verify_replayable(self) -> Result<(), MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let (set_search_path, reset_search_path, create_schema, drop_schema) = match (
+            A::Database::SET_SEARCH_PATH,
+            A::Database::RESET_SEARCH_PATH,
+            A::Database::CREATE_SCHEMA,
+            A::Database::DROP_SCHEMA,
+        ) {
+            (Some(set), Some(reset), Some(create), Some(drop)) => (set, reset, create, drop),
+            _ => return Err(MigrationError::Unsupported("Replaying migrations")),
+        };
+        verify_unique(&migrations)?;
+        dependencies::verify(&migrations, &HashSet::new())?;
+        let schema = format!(
+            "sqlx_simple_migrator_replay_{}",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+        );
+        let config = MigratorConfig {
+            search_path: None,
+            ..config.schema(&schema)
+        };
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        for statement in [create_schema, set_search_path] {
+            let sql = statement.replace("{schema}", &schema);
+            migration_try!(
+                A::Database::execute(&mut conn, &sql).await,
+                Bookkeeping {
+                    migration: None,
+                    sql,
+                }
+            );
+        }
+        let migrations = with_builtin_migrations::<A::Database>(&config, migrations);
+        hooks.before_all();
+        let result = replay::<A::Database>(&mut conn, &migrations, &config, &hooks).await;
+        hooks.after_all(&result);
+        for sql in [
+            drop_schema.replace("{schema}", &schema),
+            reset_search_path.to_owned(),
+        ] {
+            let cleanup = A::Database::execute(&mut conn, &sql).await;
+            if let (Ok(()), Err(source)) = (&result, cleanup) {
+                return Err(MigrationError::Bookkeeping {
+                    migration: None,
+                    sql,
+                    source,
+                });
+            }
+        }
+        result
+    }
+
+    /// Execute the pending migrations up to and including the migration
+    /// named `target`, leaving any later migrations pending
+    pub async fn run_until(self, target: &str) -> Result<MigrationReport, MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        if !migrations.iter().any(|m| m.name == target) {
+            return Err(MigrationError::UnknownMigration(target.to_owned()));
+        }
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        hooks.before_all();
+        let result =
+            execute_plan::<A::Database>(&mut conn, migrations, Some(target), &config, &hooks).await;
+        hooks.after_all(&result);
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Undo and then perform the migration named `target`, for iterating on
+    /// a migration without undoing every migration after it. A migration that
+    /// hasn't been performed yet is only performed.
+    pub async fn redo(self, target: &str) -> Result<(), MigrationError> {
+        let Migrator {
+            executor,
+            mut migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let position = migrations
+            .iter()
+            .position(|m| m.name == target)
+            .ok_or_else(|| MigrationError::UnknownMigration(target.to_owned()))?;
+        let migration = migrations.swap_remove(position);
+
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        hooks.before_all();
+        let result = redo_migration::<A::Database>(&mut conn, &migration, &config, &hooks).await;
+        hooks.after_all(&result);
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Record the migration named `target` as performed without executing
+    /// its statements, such as after its changes were applied by hand
+    pub async fn mark_applied(self, target: &str) -> Result<(), MigrationError> {
+        let Migrator {
+            executor,
+            mut migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let position = migrations
+            .iter()
+            .position(|m| m.name == target)
+            .ok_or_else(|| MigrationError::UnknownMigration(target.to_owned()))?;
+        let migration = migrations.swap_remove(position);
+
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        let result =
+            record_baseline::<A::Database>(&mut conn, vec![migration], &config, &hooks).await;
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Remove the record of the migration named `target` being performed
+    /// without executing its down statements, such as after its changes were
+    /// reverted by hand. The migration doesn't need to be supplied.
+    pub async fn mark_unapplied(self, target: &str) -> Result<(), MigrationError> {
+        let Migrator {
+            executor, config, ..
+        } = self;
+        if is_builtin(target) {
+            return Err(MigrationError::UnknownMigration(target.to_owned()));
+        }
+
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        let result = Migration::new(target)
+            .remove_record::<A::Database>(&mut conn, &config)
+            .await;
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Replace the recorded checksum of each applied migration whose up
+    /// statements have changed, such as after cleaning up whitespace, without
+    /// executing any statements. Each repaired migration is logged with its
+    /// previous and current checksum, and returned.
+    pub async fn repair(self) -> Result<Vec<RepairedChecksum>, MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            ..
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        let result = repair::repair::<A::Database>(&mut conn, &migrations, &config).await;
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Returns the statements that `run` would execute, without executing
+    /// them. Bookkeeping statements have their values inlined as literals.
+    pub async fn dry_run(self) -> Result<Vec<PlannedMigration>, MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            ..
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        let mut migrations = with_builtin_migrations::<A::Database>(&config, migrations);
+        apply_debug_modes(&mut migrations, &config)?;
+        verify_unique(&migrations)?;
+        verify_downs(&migrations, &config)?;
+        let mut performed_migrations =
+            performed_migrations::<A::Database>(&mut conn, &config).await?;
+        // Records that `run` would rename aren't planned
+        let renamed = file_name::legacy_names(&migrations, &performed_migrations)
+            .into_iter()
+            .map(|(legacy, m)| (legacy, m.name.clone()))
+            .collect::<Vec<_>>();
+        for (legacy, name) in renamed {
+            performed_migrations.remove(&legacy);
+            performed_migrations.insert(name);
+        }
+        // Snapshots that `run` would record as performed aren't planned
+        let adopted = snapshot::adoptable(&migrations, &performed_migrations)?
+            .into_iter()
+            .map(|m| m.name.clone())
+            .collect::<Vec<_>>();
+        performed_migrations.extend(adopted);
+        verify_known(&migrations, &performed_migrations, &config)?;
+        tags::filter(&mut migrations, &performed_migrations, &config);
+        environment::filter(&mut migrations, &performed_migrations, &config);
+        dependencies::verify(&migrations, &performed_migrations)?;
+        Migration::verify_order(&migrations, &performed_migrations, &config)?;
+
+        let keep_table = shared_table::<A::Database>(&mut conn, &config).await?;
+        let mut redo = plan::replayed(&migrations, &performed_migrations, &config)?;
+        redo.extend(
+            drift::changed::<A::Database>(&mut conn, &migrations, &performed_migrations, &config)
+                .await?,
+        );
+        let steps = plan::plan(migrations, &performed_migrations, keep_table, &redo);
+        verify_down_allowed(&steps, &config)?;
+        lint::verify_statements(&steps, &config)?;
+        extension::verify_available::<A::Database>(&mut conn, &steps).await?;
+        steps
+            .into_iter()
+            .filter_map(|step| {
+                PlannedMigration::from_step::<A::Database>(step, &config).transpose()
+            })
+            .collect()
+    }
+
+    /// Returns a single sql script performing the migrations that `run` would
+    /// perform against a database that has applied `applied_names`, including
+    /// the bookkeeping statements, for running by hand. Nothing is executed.
+    /// The crate's own bookkeeping migrations are treated as applied unless
+    /// `applied_names` is empty.
+    pub fn to_sql_script(self, applied_names: &[&str]) -> Result<String, MigrationError> {
+        let Migrator {
+            migrations, config, ..
+        } = self.load_files()?;
+        let mut migrations = with_builtin_migrations::<A::Database>(&config, migrations);
+        apply_debug_modes(&mut migrations, &config)?;
+        verify_unique(&migrations)?;
+        verify_downs(&migrations, &config)?;
+        let mut performed_migrations = applied_names
+            .iter()
+            .map(|&name| name.to_owned())
+            .collect::<HashSet<_>>();
+        if !performed_migrations.is_empty() {
+            performed_migrations.extend(
+                migrations
+                    .iter()
+                    .filter(|m| is_builtin(&m.name))
+                    .map(|m| m.name.clone()),
+            );
+        }
+        let adopted = snapshot::adoptable(&migrations, &performed_migrations)?
+            .into_iter()
+            .map(|m| m.name.clone())
+            .collect::<Vec<_>>();
+        performed_migrations.extend(adopted);
+        verify_known(&migrations, &performed_migrations, &config)?;
+        tags::filter(&mut migrations, &performed_migrations, &config);
+        environment::filter(&mut migrations, &performed_migrations, &config);
+        dependencies::verify(&migrations, &performed_migrations)?;
+        Migration::verify_order(&migrations, &performed_migrations, &config)?;
+
+        let steps = plan::plan(
+            migrations,
+            &performed_migrations,
+            config.tracked_schema.is_some(),
+            &HashSet::new(),
+        );
+        verify_down_allowed(&steps, &config)?;
+        lint::verify_statements(&steps, &config)?;
+        for step in steps.iter() {
+            if let Step::Perform(migration) = step {
+                if !migration.up_fns.is_empty() {
+                    return Err(MigrationError::Code {
+                        migration: migration.name.clone(),
+                        source: "Rust code can't be included in a sql script".into(),
+                    });
+                }
+            }
+        }
+        let mut script = String::from("-- Generated by sqlx-simple-migrator\n");
+        for planned in steps.into_iter().filter_map(|step| {
+            PlannedMigration::from_step::<A::Database>(step, &config).transpose()
+        }) {
+            let planned = planned?;
+            script.push('\n');
+            script.push_str(&planned.to_string());
+        }
+        Ok(script)
+    }
+
+    /// Returns which of the migrations have been applied, which are pending,
+    /// and which have been applied but weren't supplied, without executing
+    /// anything
+    pub async fn status(self) -> Result<MigrationStatus, MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            ..
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        status::status::<A::Database>(&mut conn, &migrations, &config).await
+    }
+
+    /// Returns true if any of the migrations selected by the tag filter hasn't
+    /// been applied, such as for a readiness check or a pre-deploy gate.
+    /// Nothing is executed, and no tables are created or locks taken.
+    pub async fn has_pending_migrations(self) -> Result<bool, MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            ..
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        let status = status::status::<A::Database>(&mut conn, &migrations, &config).await?;
+        Ok(migrations
+            .iter()
+            .any(|m| status.pending.contains(&m.name) && config.tag_filter.matches(m)))
+    }
+
+    /// Returns `MigrationError::OutOfSync` if any migration is pending, any
+    /// applied migration wasn't supplied, or any applied migration has
+    /// changed since it was applied, such as to check a staging database in
+    /// CI before deploying. Nothing is executed.
+    pub async fn check(self) -> Result<(), MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            ..
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        status::check::<A::Database>(&mut conn, &migrations, &config).await
+    }
+
+    /// Run the down scripts to undo all of the performed migrations
+    pub async fn undo_all(self) -> Result<(), MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        let migrations = with_builtin_migrations::<A::Database>(&config, migrations);
+        hooks.before_all();
+        let result = undo_performed::<A::Database>(&mut conn, migrations, &config, &hooks).await;
+        hooks.after_all(&result);
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Undo every performed migration that comes after the migration named
+    /// `target`, in reverse order. The target migration itself remains
+    /// applied.
+    pub async fn rollback_to(self, target: &str) -> Result<(), MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let mut migrations = with_builtin_migrations::<A::Database>(&config, migrations);
+        let position = migrations
+            .iter()
+            .position(|m| m.name == target)
+            .ok_or_else(|| MigrationError::UnknownMigration(target.to_owned()))?;
+        let migrations = migrations.split_off(position + 1);
+
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        hooks.before_all();
+        let result = undo_performed::<A::Database>(&mut conn, migrations, &config, &hooks).await;
+        hooks.after_all(&result);
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Undo the `steps` most recently applied migrations, in reverse order.
+    /// The crate's own bookkeeping migrations are never rolled back by this
+    /// function.
+    pub async fn rollback(self, steps: usize) -> Result<(), MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        hooks.before_all();
+        let result =
+            rollback_steps::<A::Database>(&mut conn, migrations, steps, &config, &hooks).await;
+        hooks.after_all(&result);
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Undo the migrations executed by the last run, in reverse order, such
+    /// as to undo whatever the last deploy applied. Migrations recorded
+    /// without being executed, like those recorded by `baseline`, aren't part
+    /// of any run.
+    pub async fn rollback_last_batch(self) -> Result<(), MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        hooks.before_all();
+        let result = rollback_batch::<A::Database>(&mut conn, migrations, &config, &hooks).await;
+        hooks.after_all(&result);
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Record every migration up to and including the migration named
+    /// `target` as performed, without executing their statements. This
+    /// adopts a database whose schema already matches those migrations, so
+    /// that `run` only executes the migrations that come after `target`.
+    pub async fn baseline(self, target: &str) -> Result<(), MigrationError> {
+        let Migrator {
+            executor,
+            mut migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let position = migrations
+            .iter()
+            .position(|m| m.name == target)
+            .ok_or_else(|| MigrationError::UnknownMigration(target.to_owned()))?;
+        migrations.truncate(position + 1);
+
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        hooks.before_all();
+        let result = record_baseline::<A::Database>(&mut conn, migrations, &config, &hooks).await;
+        hooks.after_all(&result);
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+
+    /// Record the migrations that sqlx-cli has applied, according to its
+    /// `_sqlx_migrations` table, as performed. Each entry is matched to the
+    /// supplied migration whose name starts with the same version. Returns
+    /// every entry in sqlx-cli's history along with the migration it was
+    /// matched to.
+    pub async fn import_from_sqlx(self) -> Result<Vec<ImportedMigration>, MigrationError> {
+        let statement = <A::Database as MigrationBackend>::SELECT_SQLX_MIGRATIONS;
+        self.import_history(statement).await
+    }
+
+    /// Record the versioned migrations that Flyway has applied, according to
+    /// its `flyway_schema_history` table, as performed. Each entry is matched
+    /// to the supplied migration named after its script, such as
+    /// `V1_2__create_users`, or otherwise whose name starts with the same
+    /// version. Returns every entry along with the migration it was matched
+    /// to.
+    pub async fn import_from_flyway(self) -> Result<Vec<ImportedMigration>, MigrationError> {
+        let statement = <A::Database as MigrationBackend>::SELECT_FLYWAY_MIGRATIONS;
+        self.import_history(statement).await
+    }
+
+    /// Record the migrations that Diesel has applied, according to its
+    /// `__diesel_schema_migrations` table, as performed. Each entry is matched
+    /// to the supplied migration whose name starts with the same version, such
+    /// as `2021-01-01-120000_create_users`. Returns every entry along with the
+    /// migration it was matched to.
+    pub async fn import_from_diesel(self) -> Result<Vec<ImportedMigration>, MigrationError> {
+        let statement = <A::Database as MigrationBackend>::SELECT_DIESEL_MIGRATIONS;
+        self.import_history(statement).await
+    }
+
+    /// Returns how `import_from_flyway` would match Flyway's history to the
+    /// supplied migrations, without recording anything
+    pub async fn dry_run_import_from_flyway(
+        self,
+    ) -> Result<Vec<ImportedMigration>, MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            ..
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        import::map_history::<A::Database>(
+            &mut conn,
+            &migrations,
+            <A::Database as MigrationBackend>::SELECT_FLYWAY_MIGRATIONS,
+        )
+        .await
+    }
+
+    async fn import_history(
+        self,
+        statement: &str,
+    ) -> Result<Vec<ImportedMigration>, MigrationError> {
+        let Migrator {
+            executor,
+            migrations,
+            config,
+            hooks,
+        } = self.load_files()?;
+        let mut conn = migration_try!(executor.acquire().await, Connection {});
+        acquire_lock::<A::Database>(&mut conn, &config).await?;
+        let result =
+            import_history::<A::Database>(&mut conn, &migrations, statement, &config, &hooks).await;
+        release_lock::<A::Database, _>(&mut conn, &config, result).await
+    }
+}
+
+/// Waits for the configured advisory lock, if any
+async fn acquire_lock<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    config: &MigratorConfig,
+) -> Result<(), MigrationError> {
+    if let (Some(key), Some(acquire)) = (config.lock_key, DB::ACQUIRE_LOCK) {
+        migration_try!(
+            DB::execute_with_key(conn, acquire, key).await,
+            Lock {
+                sql: acquire.to_owned(),
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Acquires the configured advisory lock, returning true if the migrations
+/// should be executed. With leader election, returns false instead once
+/// another process holding the lock has applied the migrations.
+async fn lead_or_follow<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    config: &MigratorConfig,
+) -> Result<bool, MigrationError> {
+    let (key, timeout, try_acquire) = match (
+        config.lock_key,
+        config.follower_timeout,
+        DB::TRY_ACQUIRE_LOCK,
+    ) {
+        (Some(key), Some(timeout), Some(try_acquire)) => (key, timeout, try_acquire),
+        _ => {
+            acquire_lock::<DB>(conn, config).await?;
+            return Ok(true);
+        }
+    };
+    let started = Instant::now();
+    loop {
+        let acquired = migration_try!(
+            DB::try_lock(conn, try_acquire, key).await,
+            Lock {
+                sql: try_acquire.to_owned(),
+            }
+        );
+        if acquired {
+            return Ok(true);
+        }
+        let status = status::status::<DB>(conn, migrations, config).await?;
+        if !migrations
+            .iter()
+            .any(|m| status.pending.contains(&m.name) && config.tag_filter.matches(m))
+        {
+            return Ok(false);
+        }
+        if started.elapsed() >= timeout {
+            return Err(MigrationError::FollowerTimeout(timeout));
+        }
+        logging::waiting_for_leader();
+        tokio::time::sleep(config.follower_poll_interval).await;
+    }
+}
+
+/// Releases the configured advisory lock, if any. An error from the work done
+/// while holding the lock takes precedence over an error releasing it.
+async fn release_lock<DB: MigrationBackend, T>(
+    conn: &mut DB::Connection,
+    config: &MigratorConfig,
+    result: Result<T, MigrationError>,
+) -> Result<T, MigrationError> {
+    if let (Some(key), Some(release)) = (config.lock_key, DB::RELEASE_LOCK) {
+        let released = DB::execute_with_key(conn, release, key).await;
+        if result.is_ok() {
+            migration_try!(
+                released,
+                Lock {
+                    sql: release.to_owned(),
+                }
+            );
+        }
+    }
+    result
+}
+
+/// Undoes the performed migrations out of `migrations`, in reverse order
+async fn undo_performed<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: Vec<Migration>,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    let performed_migrations = performed_migrations::<DB>(conn, config).await?;
+    let keep_table = shared_table::<DB>(conn, config).await?;
+    for migration in migrations
+        .into_iter()
+        .rev()
+        .filter(|m| performed_migrations.contains(&m.name) && !(keep_table && is_builtin(&m.name)))
+    {
+        execute_migration::<DB>(conn, &migration, Direction::Down, config, hooks).await?;
+    }
+    Ok(())
+}
+
+/// Applies, undoes and reapplies each of `migrations`, identifying the
+/// migration and phase of the first failure
+async fn replay<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    let phases = [
+        ("applying", Direction::Up),
+        ("undoing", Direction::Down),
+        ("reapplying", Direction::Up),
+    ];
+    for (phase, direction) in phases {
+        let ordered: Box<dyn Iterator<Item = &Migration>> = match direction {
+            Direction::Up => Box::new(migrations.iter()),
+            Direction::Down => Box::new(migrations.iter().rev()),
+        };
+        for migration in ordered {
+            execute_migration::<DB>(conn, migration, direction, config, hooks)
+                .await
+                .map_err(|source| MigrationError::NotReplayable {
+                    migration: migration.name.clone(),
+                    phase,
+                    source: Box::new(source),
+                })?;
+        }
+    }
+    Ok(())
+}
+
+/// Undoes the last `steps` performed migrations out of `migrations`
+async fn rollback_steps<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: Vec<Migration>,
+    steps: usize,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    let performed_migrations = performed_migrations::<DB>(conn, config).await?;
+    let mut applied = migrations
+        .into_iter()
+        .filter(|m| performed_migrations.contains(&m.name))
+        .collect::<Vec<_>>();
+    let newest = applied.split_off(applied.len().saturating_sub(steps));
+    for migration in newest.into_iter().rev() {
+        execute_migration::<DB>(conn, &migration, Direction::Down, config, hooks).await?;
+    }
+    Ok(())
+}
+
+/// Undoes the performed migrations out of `migrations` that were executed by
+/// the last run, in reverse order
+async fn rollback_batch<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: Vec<Migration>,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    let performed_migrations = performed_migrations::<DB>(conn, config).await?;
+    if !performed_migrations.contains(migration_7_batch::NAME) {
+        return Ok(());
+    }
+    let table = config.qualified_table_name();
+    let batch = migration_try!(
+        DB::last_batch(conn, &table, config.recorded_schema()).await,
+        Bookkeeping {
+            migration: None,
+            sql: render(DB::SELECT_LAST_BATCH, &table),
+        }
+    );
+    if let Some(unknown) = batch
+        .iter()
+        .find(|name| !migrations.iter().any(|m| &m.name == *name))
+    {
+        return Err(MigrationError::UnknownMigration(unknown.clone()));
+    }
+    for migration in migrations
+        .into_iter()
+        .rev()
+        .filter(|m| batch.contains(&m.name) && performed_migrations.contains(&m.name))
+    {
+        execute_migration::<DB>(conn, &migration, Direction::Down, config, hooks).await?;
+    }
+    Ok(())
+}
+
+/// Undoes `migration` if it has been performed, then performs it
+async fn redo_migration<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migration: &Migration,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    execute_builtin_migrations::<DB>(conn, config, hooks).await?;
+    let performed_migrations = performed_migrations::<DB>(conn, config).await?;
+    if performed_migrations.contains(&migration.name) {
+        execute_migration::<DB>(conn, migration, Direction::Down, config, hooks).await?;
+    }
+    execute_migration::<DB>(conn, migration, Direction::Up, config, hooks).await?;
+    Ok(())
+}
+
+/// Creates the migrations table if needed, then records the unperformed
+/// migrations out of `migrations` as performed in a single transaction
+async fn record_baseline<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: Vec<Migration>,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    execute_builtin_migrations::<DB>(conn, config, hooks).await?;
+    let performed_migrations = performed_migrations::<DB>(conn, config).await?;
+    let mut tx = migration_try!(
+        conn.begin().await,
+        Transaction {
+            migration: None,
+            sql: String::from("BEGIN"),
+        }
+    );
+    for migration in migrations
+        .iter()
+        .filter(|m| !performed_migrations.contains(&m.name))
+    {
+        migration.record::<DB>(&mut tx, config, None).await?;
+    }
+    migration_try!(
+        tx.commit().await,
+        Transaction {
+            migration: None,
+            sql: String::from("COMMIT"),
+        }
+    );
+    Ok(())
+}
+
+/// Creates the migrations table if needed, then imports another tool's
+/// history read by `statement`
+async fn import_history<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    statement: &str,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<Vec<ImportedMigration>, MigrationError> {
+    execute_builtin_migrations::<DB>(conn, config, hooks).await?;
+    import::import::<DB>(conn, migrations, statement, config).await
+}
+
+/// Performs or undoes a single migration, calling the migration hooks and
+/// retrying transient failures as configured. Returns how long the
+/// successful attempt took to execute, or `None` if the migration was skipped
+/// because its condition wasn't met.
+async fn execute_migration<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migration: &Migration,
+    direction: Direction,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<Option<Duration>, MigrationError> {
+    if direction == Direction::Down && config.forbid_down {
+        return Err(MigrationError::DownForbidden(migration.name.clone()));
+    }
+    config.check_interrupted(&migration.name)?;
+    confirm::<DB>(migration, direction, config, hooks).await?;
+    hooks.before_migration(&migration.name, direction);
+    let mut attempt = 0;
+    let result = loop {
+        let started = Instant::now();
+        let result = match direction {
+            Direction::Up => migration.perform::<DB>(conn, config, hooks).await,
+            Direction::Down => migration
+                .undo::<DB>(conn, config, hooks)
+                .await
+                .map(|_| true),
+        };
+        match &result {
+            Err(err)
+                if attempt < config.retries
+                    && migration.single_transaction()
+                    && err.is_transient::<DB>() =>
+            {
+                let backoff = retry_backoff(config.retry_backoff, attempt);
+                logging::warning(
+                    &migration.name,
+                    &format!("retrying in {:?} after a transient error: {}", backoff, err),
+                );
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            _ => break result.map(|performed| performed.then(|| started.elapsed())),
+        }
+    };
+    hooks.after_migration(&migration.name, direction, &result);
+    hooks.record_metrics(&migration.name, direction, &result);
+    result
+}
+
+/// Returns how long to wait before retry number `attempt`, counted from 0:
+/// `backoff` doubled for each earlier retry, but no longer than
+/// `MAX_RETRY_BACKOFF`
+fn retry_backoff(backoff: Duration, attempt: u32) -> Duration {
+    2u32.checked_pow(attempt)
+        .and_then(|factor| backoff.checked_mul(factor))
+        .map_or(MAX_RETRY_BACKOFF, |backoff| backoff.min(MAX_RETRY_BACKOFF))
+}
+
+/// Awaits the confirmation callback before `migration` is undone, or
+/// performed when it's destructive
+async fn confirm<DB: MigrationBackend>(
+    migration: &Migration,
+    direction: Direction,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    let confirm = match &hooks.confirm {
+        Some(confirm) => confirm,
+        None => return Ok(()),
+    };
+    let step = match direction {
+        _ if is_builtin(&migration.name) => return Ok(()),
+        Direction::Up if !migration.tags.iter().any(|tag| tag == tags::DESTRUCTIVE) => {
+            return Ok(())
+        }
+        Direction::Up => Step::Perform(migration.clone()),
+        Direction::Down => Step::Undo(migration.clone()),
+    };
+    if let Some(planned) = PlannedMigration::from_step::<DB>(step, config)? {
+        if !confirm(planned).await {
+            return Err(MigrationError::NotConfirmed(migration.name.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that none of `steps` undo a migration when undoing migrations is
+/// forbidden, so that a debug mode fails before anything is executed
+fn verify_down_allowed(steps: &[Step], config: &MigratorConfig) -> Result<(), MigrationError> {
+    if !config.forbid_down {
+        return Ok(());
+    }
+    match steps.iter().find_map(|step| match step {
+        Step::Undo(migration) => Some(migration),
+        _ => None,
+    }) {
+        Some(migration) => Err(MigrationError::DownForbidden(migration.name.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Executes the pending migrations, stopping after the migration named
+/// `until` when it is set
+async fn execute_plan<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: Vec<Migration>,
+    until: Option<&str>,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<MigrationReport, MigrationError> {
+    if config.notify_channel.is_some() && DB::NOTIFY.is_none() {
+        return Err(MigrationError::Unsupported("Notifying listeners"));
+    }
+    if DB::SET_ISOLATION_LEVEL.is_none() && migrations.iter().any(|m| m.isolation.is_some()) {
+        return Err(MigrationError::Unsupported("Setting the isolation level"));
+    }
+    if config.search_path.is_some() && DB::SET_LOCAL_SEARCH_PATH.is_none() {
+        return Err(MigrationError::Unsupported("Setting the search path"));
+    }
+    let mut migrations = with_builtin_migrations::<DB>(config, migrations);
+    apply_debug_modes(&mut migrations, config)?;
+    verify_unique(&migrations)?;
+    verify_downs(&migrations, config)?;
+    // The table is upgraded first, so that the records renamed or adopted
+    // below are written with the current columns
+    execute_builtin_migrations::<DB>(conn, config, hooks).await?;
+    let performed_migrations = performed_migrations::<DB>(conn, config).await?;
+    let performed_migrations =
+        file_name::rename_legacy::<DB>(conn, &migrations, performed_migrations, config).await?;
+    let performed_migrations =
+        snapshot::adopt::<DB>(conn, &migrations, performed_migrations, config).await?;
+    verify_known(&migrations, &performed_migrations, config)?;
+    tags::filter(&mut migrations, &performed_migrations, config);
+    environment::filter(&mut migrations, &performed_migrations, config);
+    dependencies::verify(&migrations, &performed_migrations)?;
+    Migration::verify_order(&migrations, &performed_migrations, config)?;
+    let mut redo = plan::replayed(&migrations, &performed_migrations, config)?;
+    redo.extend(drift::changed::<DB>(conn, &migrations, &performed_migrations, config).await?);
+    if let Some(position) = until.and_then(|until| migrations.iter().position(|m| m.name == until))
+    {
+        migrations.truncate(position + 1);
+    }
+    let batch = next_batch::<DB>(conn, config).await?;
+    let config = &MigratorConfig {
+        batch: Some(batch),
+        ..config.clone()
+    };
+    let report = execute_steps::<DB>(
+        conn,
+        migrations,
+        &performed_migrations,
+        &redo,
+        config,
+        hooks,
+    )
+    .await?;
+    notify_applied::<DB>(conn, &report, config).await?;
+    Ok(report)
+}
+
+/// Returns the batch recorded with the migrations of a run that starts now
+async fn next_batch<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    config: &MigratorConfig,
+) -> Result<i64, MigrationError> {
+    let statement = render(DB::SELECT_NEXT_BATCH, &config.qualified_table_name());
+    let batch = migration_try!(
+        DB::query_bound(conn, &statement).await,
+        Bookkeeping {
+            migration: None,
+            sql: statement,
+        }
+    );
+    Ok(batch.unwrap_or(1))
+}
+
+/// Notifies listeners on `config.notify_channel` with the name of the last
+/// migration applied, if any were
+async fn notify_applied<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    report: &MigrationReport,
+    config: &MigratorConfig,
+) -> Result<(), MigrationError> {
+    let (channel, latest) = match (&config.notify_channel, report.applied.last()) {
+        (Some(channel), Some(latest)) => (channel, latest),
+        _ => return Ok(()),
+    };
+    let notify = match DB::NOTIFY {
+        Some(notify) => notify,
+        None => return Ok(()),
+    };
+    migration_try!(
+        DB::execute_with_values(conn, notify, &[channel.as_str(), latest.name.as_str()]).await,
+        Bookkeeping {
+            migration: None,
+            sql: notify.to_owned(),
+        }
+    );
+    Ok(())
+}
+
+/// Creates or upgrades the migrations table as needed
+async fn execute_builtin_migrations<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<(), MigrationError> {
+    let migrations = with_builtin_migrations::<DB>(config, Vec::new());
+    let performed_migrations = performed_migrations::<DB>(conn, config).await?;
+    execute_steps::<DB>(
+        conn,
+        migrations,
+        &performed_migrations,
+        &HashSet::new(),
+        config,
+        hooks,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn execute_steps<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: Vec<Migration>,
+    performed_migrations: &HashSet<String>,
+    redo: &HashSet<String>,
+    config: &MigratorConfig,
+    hooks: &Hooks,
+) -> Result<MigrationReport, MigrationError> {
+    let mut report = MigrationReport::default();
+    let keep_table = shared_table::<DB>(conn, config).await?;
+    let steps = plan::plan(migrations, performed_migrations, keep_table, redo);
+    verify_down_allowed(&steps, config)?;
+    lint::verify_statements(&steps, config)?;
+    extension::verify_available::<DB>(conn, &steps).await?;
+    for step in steps {
+        match step {
+            Step::Perform(migration) => {
+                let duration =
+                    execute_migration::<DB>(conn, &migration, Direction::Up, config, hooks).await?;
+                if is_builtin(&migration.name) {
+                    continue;
+                }
+                match duration {
+                    Some(duration) => report.applied.push(ReportedMigration {
+                        name: migration.name,
+                        duration,
+                    }),
+                    None => report.skipped.push(migration.name),
+                }
+            }
+            Step::Undo(migration) => {
+                let duration =
+                    execute_migration::<DB>(conn, &migration, Direction::Down, config, hooks)
+                        .await?;
+                if !is_builtin(&migration.name) {
+                    report.rolled_back.push(ReportedMigration {
+                        name: migration.name,
+                        duration: duration.unwrap_or_default(),
+                    });
+                }
+            }
+            Step::VerifyChecksum(migration) => {
+                if !migration.verify_checksum::<DB>(conn, config).await? {
+                    report.skipped.push(migration.name);
+                    continue;
+                }
+                let duration =
+                    execute_migration::<DB>(conn, &migration, Direction::Up, config, hooks).await?;
+                report.applied.push(ReportedMigration {
+                    name: migration.name,
+                    duration: duration.unwrap_or_default(),
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubles_the_retry_backoff() {
+        let backoff = Duration::from_millis(100);
+        assert_eq!(retry_backoff(backoff, 0), Duration::from_millis(100));
+        assert_eq!(retry_backoff(backoff, 1), Duration::from_millis(200));
+        assert_eq!(retry_backoff(backoff, 3), Duration::from_millis(800));
+    }
+
+    #[test]
+    fn caps_the_retry_backoff() {
+        let backoff = Duration::from_millis(100);
+        assert_eq!(retry_backoff(backoff, 20), MAX_RETRY_BACKOFF);
+        assert_eq!(retry_backoff(backoff, u32::MAX), MAX_RETRY_BACKOFF);
+        assert_eq!(
+            retry_backoff(Duration::from_secs(u64::MAX), 1),
+            MAX_RETRY_BACKOFF
+        );
+        assert_eq!(
+            retry_backoff(Duration::from_secs(3600), 0),
+            MAX_RETRY_BACKOFF
+        );
+    }
+}
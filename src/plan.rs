@@ -0,0 +1,319 @@
+use super::{
+    is_builtin, logging, migration_0_initial, render, DebugModes, Migration, MigrationBackend,
+    MigrationError, MigratorConfig, Mode,
+};
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+};
+
+/// Stands in for a migration's Rust code in a plan
+const RUST_CODE: &str = "-- Executes Rust code";
+
+/// A single step of executing a set of migrations
+pub(crate) enum Step {
+    Perform(Migration),
+    Undo(Migration),
+    VerifyChecksum(Migration),
+}
+
+/// Resolves the steps needed to bring the database up to date with
+/// `migrations`, given the names of the migrations already performed. The
+/// crate's own migrations aren't rolled back when `keep_table` is set. The
+/// applied migrations in `redo` are undone in reverse order before the first
+/// of them, then performed again in place.
+pub(crate) fn plan(
+    migrations: Vec<Migration>,
+    performed_migrations: &HashSet<String>,
+    keep_table: bool,
+    redo: &HashSet<String>,
+) -> Vec<Step> {
+    let mut steps = Vec::new();
+    if migrations.iter().any(|m| Mode::NuclearDebug == m.mode) {
+        // If any migration is nuclear, roll everything back, then execute all the migraitons again
+        let shared = |m: &Migration| keep_table && is_builtin(&m.name);
+        steps.extend(
+            migrations
+                .iter()
+                .rev()
+                .filter(|m| !shared(m))
+                .cloned()
+                .map(Step::Undo),
+        );
+        steps.extend(
+            migrations
+                .into_iter()
+                .filter(|m| !shared(m) || !performed_migrations.contains(&m.name))
+                .map(Step::Perform),
+        );
+    } else {
+        let mut redone = migrations
+            .iter()
+            .filter(|m| redo.contains(&m.name))
+            .cloned()
+            .collect::<Vec<_>>();
+        for migration in migrations {
+            let mut performed = performed_migrations.contains(&migration.name);
+            if redo.contains(&migration.name) {
+                steps.extend(redone.drain(..).rev().map(Step::Undo));
+                performed = false;
+            }
+            if let Mode::Debug = migration.mode {
+                steps.push(Step::Undo(migration.clone()));
+                performed = false;
+            }
+
+            if !performed {
+                steps.push(Step::Perform(migration));
+            } else if !is_builtin(&migration.name) {
+                steps.push(Step::VerifyChecksum(migration));
+            }
+        }
+    }
+    steps
+}
+
+/// Returns the names of the applied migrations from `config.nuclear_debug_from`
+/// onward, which are rolled back and performed again like a nuclear debug
+/// migration, subject to `config.debug_modes`
+pub(crate) fn replayed(
+    migrations: &[Migration],
+    performed_migrations: &HashSet<String>,
+    config: &MigratorConfig,
+) -> Result<HashSet<String>, MigrationError> {
+    let from = match &config.nuclear_debug_from {
+        Some(from) => from,
+        None => return Ok(HashSet::new()),
+    };
+    let position = migrations
+        .iter()
+        .position(|m| &m.name == from)
+        .ok_or_else(|| MigrationError::UnknownMigration(from.clone()))?;
+    match config.debug_modes {
+        DebugModes::Execute => {}
+        DebugModes::Stable => {
+            logging::warning(from, "is in a debug mode, executing it as stable");
+            return Ok(HashSet::new());
+        }
+        DebugModes::Error => return Err(MigrationError::DebugMode(from.clone())),
+    }
+    Ok(migrations[position..]
+        .iter()
+        .filter(|m| !is_builtin(&m.name) && performed_migrations.contains(&m.name))
+        .map(|m| m.name.clone())
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Whether a migration is being applied or rolled back
+pub enum Direction {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The statements that would be executed to perform or undo a migration
+pub struct PlannedMigration {
+    pub name: String,
+    pub direction: Direction,
+    pub statements: Vec<String>,
+}
+
+/// Adds `statement` to `statements`, within a transaction of its own after
+/// the `setup` statements when they're given
+fn push_statement(statements: &mut Vec<String>, setup: &Option<Vec<String>>, statement: String) {
+    match setup {
+        Some(setup) => {
+            statements.push(String::from("BEGIN"));
+            statements.extend(setup.iter().cloned());
+            statements.push(statement);
+            statements.push(String::from("COMMIT"));
+        }
+        None => statements.push(statement),
+    }
+}
+
+impl PlannedMigration {
+    pub(crate) fn from_step<DB: MigrationBackend>(
+        step: Step,
+        config: &MigratorConfig,
+    ) -> Result<Option<Self>, MigrationError> {
+        let (migration, direction) = match step {
+            Step::Perform(migration) => (migration, Direction::Up),
+            Step::Undo(migration) => (migration, Direction::Down),
+            Step::VerifyChecksum(_) => return Ok(None),
+        };
+
+        let table = config.qualified_table_name();
+        let (mut statements, after) = migration.session_statements::<DB>(config)?;
+        if migration.single_transaction() {
+            statements.push(String::from("BEGIN"));
+            statements.extend(migration.transaction_statements::<DB>(config)?);
+        }
+        let setup = if migration.statement_transactions() {
+            Some(migration.transaction_statements::<DB>(config)?)
+        } else {
+            None
+        };
+        match direction {
+            Direction::Up => {
+                statements.extend(migration.condition.as_deref().map(|condition| {
+                    format!("-- Only if: {}", config.substitute_vars(condition).trim())
+                }));
+                let mut functions = migration.up_fns.iter().map(|f| f.position).peekable();
+                for (index, statement) in migration.up.iter().enumerate() {
+                    while functions.next_if_eq(&index).is_some() {
+                        statements.push(String::from(RUST_CODE));
+                    }
+                    push_statement(&mut statements, &setup, config.substitute_vars(statement));
+                }
+                statements.extend(functions.map(|_| String::from(RUST_CODE)));
+                statements.push(match migration.recorded_checksum() {
+                    // The duration isn't known until the migration is executed
+                    Some(checksum) => bind_literals(
+                        &render(DB::INSERT_MIGRATION_WITH_DETAILS, &table),
+                        &[
+                            Some(&migration.name),
+                            Some(&checksum),
+                            None,
+                            Some(&migration.statement_count().to_string()),
+                            config.hostname.as_deref(),
+                            config.app_version.as_deref(),
+                            config.build_id.as_deref(),
+                            migration.description.as_deref(),
+                            Some(config.recorded_schema()),
+                            config.batch.map(|batch| batch.to_string()).as_deref(),
+                            Some(if migration.repeatable { "1" } else { "0" }),
+                        ],
+                    )?,
+                    None => bind_literals(
+                        &render(DB::INSERT_MIGRATION, &table),
+                        &[Some(&migration.name)],
+                    )?,
+                });
+            }
+            Direction::Down => {
+                for statement in migration.down_statements(config) {
+                    push_statement(&mut statements, &setup, config.substitute_vars(&statement));
+                }
+                if migration.name != migration_0_initial::NAME {
+                    for name in std::iter::once(&migration.name).chain(migration.replaces.iter()) {
+                        statements.push(if is_builtin(name) {
+                            bind_literals(&render(DB::DELETE_MIGRATION, &table), &[Some(name)])?
+                        } else {
+                            bind_literals(
+                                &render(DB::DELETE_MIGRATION_WITH_SCHEMA, &table),
+                                &[Some(name), Some(config.recorded_schema())],
+                            )?
+                        });
+                    }
+                }
+            }
+        }
+        if migration.single_transaction() {
+            statements.push(String::from("COMMIT"));
+        }
+        statements.extend(after);
+
+        Ok(Some(PlannedMigration {
+            name: migration.name,
+            direction,
+            statements,
+        }))
+    }
+}
+
+impl Display for PlannedMigration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let action = match self.direction {
+            Direction::Up => "Performing",
+            Direction::Down => "Undoing",
+        };
+        writeln!(f, "-- {} {}", action, self.name)?;
+        for statement in self.statements.iter() {
+            writeln!(f, "{};", statement.trim().trim_end_matches(';'))?;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the `$N` or `?` placeholders in one of the bookkeeping statements
+/// with quoted string literals, or `NULL`. A `$` that isn't followed by a
+/// number is left as it is. Returns an error if a placeholder has no value.
+fn bind_literals(sql: &str, values: &[Option<&str>]) -> Result<String, MigrationError> {
+    let quote = |value: Option<&Option<&str>>| match value {
+        Some(Some(value)) => Ok(format!("'{}'", value.replace('\'', "''"))),
+        Some(None) => Ok(String::from("NULL")),
+        None => Err(MigrationError::Bookkeeping {
+            migration: None,
+            sql: sql.to_owned(),
+            source: sqlx::Error::Protocol(format!(
+                "only {} values were supplied for the placeholders",
+                values.len()
+            )),
+        }),
+    };
+    let mut bound = String::with_capacity(sql.len());
+    let mut next_value = values.iter();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '?' => bound.push_str(&quote(next_value.next())?),
+            '$' => {
+                let mut index = None;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    index = Some(index.unwrap_or(0) * 10 + digit as usize);
+                    chars.next();
+                }
+                match index {
+                    Some(index) => {
+                        bound.push_str(&quote(index.checked_sub(1).and_then(|i| values.get(i)))?)
+                    }
+                    None => bound.push('$'),
+                }
+            }
+            c => bound.push(c),
+        }
+    }
+    Ok(bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_question_mark_placeholders_in_order() {
+        assert_eq!(
+            bind_literals("INSERT INTO t (a, b) VALUES (?, ?)", &[Some("it's"), None]).unwrap(),
+            "INSERT INTO t (a, b) VALUES ('it''s', NULL)"
+        );
+    }
+
+    #[test]
+    fn binds_numbered_placeholders() {
+        let values = (1..=10).map(|i| i.to_string()).collect::<Vec<_>>();
+        let values = values.iter().map(|v| Some(v.as_str())).collect::<Vec<_>>();
+        assert_eq!(
+            bind_literals("SELECT $10, $2, $1", &values).unwrap(),
+            "SELECT '10', '2', '1'"
+        );
+    }
+
+    #[test]
+    fn leaves_a_bare_dollar_sign() {
+        assert_eq!(
+            bind_literals("SELECT '$' || $1, $$", &[Some("a")]).unwrap(),
+            "SELECT '$' || 'a', $$"
+        );
+    }
+
+    #[test]
+    fn returns_an_error_for_missing_values() {
+        assert!(bind_literals("VALUES (?, ?)", &[Some("a")]).is_err());
+        assert!(bind_literals("VALUES ($2)", &[Some("a")]).is_err());
+        assert!(bind_literals("VALUES ($0)", &[Some("a")]).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+//! Migrations registered with `#[register_migration]`. Each registration is
+//! a constructor the platform runs before `main`, which adds the migration's
+//! function to the list below.
+
+use super::Migration;
+use std::sync::Mutex;
+
+static REGISTERED: Mutex<Vec<fn() -> Migration>> = Mutex::new(Vec::new());
+
+#[doc(hidden)]
+/// Called by the code `#[register_migration]` expands to
+pub fn register(migration: fn() -> Migration) {
+    REGISTERED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(migration);
+}
+
+impl Migration {
+    /// Returns the migrations registered with `#[register_migration]` in
+    /// every crate linked into the binary, ordered by name
+    pub fn registered() -> Vec<Migration> {
+        let functions = REGISTERED
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        let mut migrations = functions.iter().map(|f| f()).collect::<Vec<_>>();
+        migrations.sort_by(|a, b| a.name.cmp(&b.name));
+        migrations
+    }
+}
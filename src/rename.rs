@@ -0,0 +1,138 @@
+use super::{Backfill, Migration};
+use sqlx::Postgres;
+
+#[derive(Clone, Debug)]
+/// Generates the migrations that rename a Postgres column without downtime,
+/// using the expand and contract pattern. The expand migration adds the new
+/// column, installs a trigger that keeps both columns in sync, and copies the
+/// existing data. Once every deployed version of the application reads and
+/// writes the new column, the contract migration removes the trigger and the
+/// old column.
+pub struct ColumnRename {
+    pub table: String,
+    pub old: String,
+    pub new: String,
+    /// The type of the column, such as `TEXT`
+    pub column_type: String,
+    /// When set, existing data is copied in batches of this many rows using a
+    /// `Backfill`, and the expand migration is executed with `no_transaction`
+    pub batch_size: Option<u64>,
+}
+
+impl ColumnRename {
+    /// Create a rename of `table.old` to `table.new`, a column of
+    /// `column_type`
+    pub fn new(table: &str, old: &str, new: &str, column_type: &str) -> Self {
+        ColumnRename {
+            table: table.to_owned(),
+            old: old.to_owned(),
+            new: new.to_owned(),
+            column_type: column_type.to_owned(),
+            batch_size: None,
+        }
+    }
+
+    /// Copy the existing data in batches of `batch_size` rows, each committed
+    /// separately, instead of in a single `UPDATE`
+    pub fn batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Returns the expand and contract migrations, named `{name}_expand` and
+    /// `{name}_contract`. The contract migration must be deployed separately,
+    /// after the application has switched to the new column.
+    pub fn migrations(&self, name: &str) -> (Migration, Migration) {
+        let expand = Migration::new(&format!("{}_expand", name))
+            .with_up(&self.add_column(&self.new))
+            .with_down(&self.drop_column(&self.new))
+            .with_up(&self.create_function())
+            .with_down(&self.drop_function())
+            .with_up(&self.create_trigger())
+            .with_down(&self.drop_trigger());
+        let expand = match self.batch_size {
+            Some(batch_size) => expand
+                .with_backfill::<Postgres, _>(
+                    Backfill::new(&self.table, &self.copy(&self.new, &self.old))
+                        .filter(&format!("{} IS NULL", self.new))
+                        .batch_size(batch_size),
+                )
+                .no_transaction(),
+            None => expand.with_up(&self.copy_all(&self.new, &self.old)),
+        };
+
+        let contract = Migration::new(&format!("{}_contract", name))
+            .with_up(&self.drop_trigger())
+            .with_up(&self.drop_function())
+            .with_up(&self.drop_column(&self.old))
+            .with_down(&self.create_trigger())
+            .with_down(&self.create_function())
+            .with_down(&self.copy_all(&self.old, &self.new))
+            .with_down(&self.add_column(&self.old));
+        (expand, contract)
+    }
+
+    fn function_name(&self) -> String {
+        format!("{}_sync_{}_{}", self.table, self.old, self.new)
+    }
+
+    fn add_column(&self, column: &str) -> String {
+        format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            self.table, column, self.column_type
+        )
+    }
+
+    fn drop_column(&self, column: &str) -> String {
+        format!("ALTER TABLE {} DROP COLUMN {}", self.table, column)
+    }
+
+    fn copy(&self, to: &str, from: &str) -> String {
+        format!("{} = {}", to, from)
+    }
+
+    fn copy_all(&self, to: &str, from: &str) -> String {
+        format!("UPDATE {} SET {}", self.table, self.copy(to, from))
+    }
+
+    /// Copies whichever column was written into the other, so that both old
+    /// and new versions of the application see the same data
+    fn create_function(&self) -> String {
+        format!(
+            r#"
+            CREATE FUNCTION {function}() RETURNS trigger LANGUAGE plpgsql AS $$
+            BEGIN
+                IF TG_OP = 'INSERT' THEN
+                    NEW.{new} := COALESCE(NEW.{new}, NEW.{old});
+                    NEW.{old} := COALESCE(NEW.{old}, NEW.{new});
+                ELSIF NEW.{old} IS DISTINCT FROM OLD.{old} THEN
+                    NEW.{new} := NEW.{old};
+                ELSIF NEW.{new} IS DISTINCT FROM OLD.{new} THEN
+                    NEW.{old} := NEW.{new};
+                END IF;
+                RETURN NEW;
+            END
+            $$
+            "#,
+            function = self.function_name(),
+            old = self.old,
+            new = self.new,
+        )
+    }
+
+    fn drop_function(&self) -> String {
+        format!("DROP FUNCTION {}()", self.function_name())
+    }
+
+    fn create_trigger(&self) -> String {
+        format!(
+            "CREATE TRIGGER {function} BEFORE INSERT OR UPDATE ON {table} FOR EACH ROW EXECUTE PROCEDURE {function}()",
+            function = self.function_name(),
+            table = self.table,
+        )
+    }
+
+    fn drop_trigger(&self) -> String {
+        format!("DROP TRIGGER {} ON {}", self.function_name(), self.table)
+    }
+}
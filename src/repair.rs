@@ -0,0 +1,80 @@
+use super::{
+    is_builtin, logging, migration_6_schema, performed_migrations, render, Migration,
+    MigrationBackend, MigrationError, MigratorConfig,
+};
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// An applied migration whose recorded checksum was replaced by `repair`
+pub struct RepairedChecksum {
+    pub name: String,
+    /// The checksum recorded when the migration was applied
+    pub previous: String,
+    /// The checksum of the migration's current up statements
+    pub current: String,
+}
+
+impl Display for RepairedChecksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.name, self.previous, self.current)
+    }
+}
+
+/// Replaces the recorded checksum of each applied migration that no longer
+/// matches its up statements. Managed migrations are left for the next run to
+/// perform again.
+pub(crate) async fn repair<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    config: &MigratorConfig,
+) -> Result<Vec<RepairedChecksum>, MigrationError> {
+    let performed = performed_migrations::<DB>(conn, config).await?;
+    // Checksums are looked up by schema, so tables that haven't been upgraded
+    // are left for the next run, which stores any missing checksums
+    if !performed.contains(migration_6_schema::NAME) {
+        return Ok(Vec::new());
+    }
+    let table = config.qualified_table_name();
+    let mut repaired = Vec::new();
+    for migration in migrations
+        .iter()
+        .filter(|m| !is_builtin(&m.name) && !m.managed && performed.contains(&m.name))
+    {
+        let previous = migration_try!(
+            DB::migration_checksum(conn, &table, config.recorded_schema(), &migration.name).await,
+            Bookkeeping {
+                migration: Some(migration.name.clone()),
+                sql: render(DB::SELECT_CHECKSUM, &table),
+            }
+        );
+        let current = migration.checksum();
+        match previous {
+            Some(previous) if previous != current => {
+                migration_try!(
+                    DB::update_checksum(
+                        conn,
+                        &table,
+                        config.recorded_schema(),
+                        &migration.name,
+                        &current
+                    )
+                    .await,
+                    Bookkeeping {
+                        migration: Some(migration.name.clone()),
+                        sql: render(DB::UPDATE_CHECKSUM, &table),
+                    }
+                );
+                let checksum = RepairedChecksum {
+                    name: migration.name.clone(),
+                    previous,
+                    current,
+                };
+                logging::checksum_repaired(&checksum);
+                repaired.push(checksum);
+            }
+            _ => {}
+        }
+    }
+    Ok(repaired)
+}
@@ -0,0 +1,32 @@
+use super::MigrationError;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// What a run did to each of the supplied migrations. The crate's own
+/// bookkeeping migrations are not included.
+pub struct MigrationReport {
+    /// The migrations that were performed, in the order they were performed
+    pub applied: Vec<ReportedMigration>,
+    /// The migrations that were undone, such as by migrations in debug mode
+    pub rolled_back: Vec<ReportedMigration>,
+    /// The migrations that had already been performed, or that were skipped
+    /// because their condition wasn't met
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// A migration that was performed or undone
+pub struct ReportedMigration {
+    pub name: String,
+    /// How long the migration took to execute
+    pub duration: Duration,
+}
+
+#[derive(Debug)]
+/// The outcome of running migrations against one of several schemas
+pub struct SchemaReport {
+    pub schema: String,
+    pub result: Result<MigrationReport, MigrationError>,
+}
@@ -0,0 +1,168 @@
+/// Words of a statement, consumed from the front while matching keywords
+struct Words<'s> {
+    words: std::iter::Peekable<std::str::SplitWhitespace<'s>>,
+}
+
+impl<'s> Words<'s> {
+    /// Consumes the next word if it is `keyword`, ignoring case
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.words
+            .next_if(|word| word.eq_ignore_ascii_case(keyword))
+            .is_some()
+    }
+
+    /// Consumes the next words if they are `keywords`. Once the first keyword
+    /// matches, the remaining keywords must match too.
+    fn keywords(&mut self, keywords: &[&str]) -> Option<bool> {
+        if !self.keyword(keywords[0]) {
+            return Some(false);
+        }
+        keywords[1..]
+            .iter()
+            .all(|keyword| self.keyword(keyword))
+            .then_some(true)
+    }
+
+    /// Consumes the next word as the name of a table, column or index, which
+    /// may be immediately followed by an opening parenthesis
+    fn name(&mut self) -> Option<&'s str> {
+        let name = self.words.next()?.split('(').next()?;
+        if name.is_empty() || name.eq_ignore_ascii_case("ON") {
+            return None;
+        }
+        Some(name)
+    }
+}
+
+/// Returns the statement undoing `statement`, if it is a `CREATE TABLE`,
+/// `CREATE INDEX` or `ALTER TABLE ... ADD COLUMN` statement that creates a
+/// single named object
+pub(crate) fn reverse(statement: &str) -> Option<String> {
+    let statement = statement.trim().trim_end_matches(';');
+    let mut words = Words {
+        words: statement.split_whitespace().peekable(),
+    };
+    let if_exists = |if_not_exists: bool| if if_not_exists { "IF EXISTS " } else { "" };
+
+    if words.keyword("CREATE") {
+        if words.keyword("TABLE") {
+            let if_not_exists = words.keywords(&["IF", "NOT", "EXISTS"])?;
+            let table = words.name()?;
+            Some(format!("DROP TABLE {}{}", if_exists(if_not_exists), table))
+        } else {
+            words.keyword("UNIQUE");
+            if !words.keyword("INDEX") {
+                return None;
+            }
+            let concurrently = if words.keyword("CONCURRENTLY") {
+                "CONCURRENTLY "
+            } else {
+                ""
+            };
+            let if_not_exists = words.keywords(&["IF", "NOT", "EXISTS"])?;
+            let index = words.name()?;
+            words.keyword("ON").then(|| {
+                format!(
+                    "DROP INDEX {}{}{}",
+                    concurrently,
+                    if_exists(if_not_exists),
+                    index
+                )
+            })
+        }
+    } else if words.keywords(&["ALTER", "TABLE"])? {
+        let table_if_exists = words.keywords(&["IF", "EXISTS"])?;
+        words.keyword("ONLY");
+        let table = words.name()?;
+        if !words.keyword("ADD") || has_top_level_comma(statement) {
+            return None;
+        }
+        words.keyword("COLUMN");
+        let if_not_exists = words.keywords(&["IF", "NOT", "EXISTS"])?;
+        let column = words.name()?;
+        let constraint = ["CONSTRAINT", "PRIMARY", "UNIQUE", "FOREIGN", "CHECK"];
+        if constraint.iter().any(|c| column.eq_ignore_ascii_case(c)) {
+            return None;
+        }
+        Some(format!(
+            "ALTER TABLE {}{} DROP COLUMN {}{}",
+            if_exists(table_if_exists),
+            table,
+            if_exists(if_not_exists),
+            column
+        ))
+    } else {
+        None
+    }
+}
+
+/// Returns true if `statement` has a comma outside of parentheses or quotes,
+/// such as an `ALTER TABLE` adding multiple columns
+fn has_top_level_comma(statement: &str) -> bool {
+    let mut depth = 0_usize;
+    let mut quote = None;
+    for c in statement.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, ',') if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Migration, MigratorConfig};
+
+    #[test]
+    fn reverses_single_object_statements() {
+        assert_eq!(
+            reverse("CREATE TABLE IF NOT EXISTS orders (id BIGINT);").as_deref(),
+            Some("DROP TABLE IF EXISTS orders")
+        );
+        assert_eq!(
+            reverse("CREATE UNIQUE INDEX CONCURRENTLY orders_total ON orders (total)").as_deref(),
+            Some("DROP INDEX CONCURRENTLY orders_total")
+        );
+        assert_eq!(
+            reverse("ALTER TABLE orders ADD COLUMN total NUMERIC(10, 2)").as_deref(),
+            Some("ALTER TABLE orders DROP COLUMN total")
+        );
+    }
+
+    #[test]
+    fn rejects_other_statements() {
+        assert_eq!(
+            reverse("ALTER TABLE orders ADD COLUMN a INT, ADD COLUMN b INT"),
+            None
+        );
+        assert_eq!(
+            reverse("ALTER TABLE orders ADD CONSTRAINT positive CHECK (total > 0)"),
+            None
+        );
+        assert_eq!(reverse("UPDATE orders SET total = 0"), None);
+    }
+
+    #[test]
+    fn with_reversible_marks_underivable_migrations_irreversible() {
+        let config = MigratorConfig::default();
+        let reversible =
+            Migration::new("reversible").with_reversible("CREATE TABLE orders (id BIGINT)");
+        assert!(!reversible.irreversible);
+        assert_eq!(
+            reversible.down_statements(&config),
+            vec!["DROP TABLE orders"]
+        );
+
+        let irreversible = reversible.with_reversible("UPDATE orders SET id = 1");
+        assert!(irreversible.irreversible);
+        assert_eq!(irreversible.up.len(), 2);
+        assert!(irreversible.down_statements(&config).is_empty());
+    }
+}
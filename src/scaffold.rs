@@ -0,0 +1,153 @@
+//! Generates the files for a new migration, named after the current time so
+//! that migrations created on different branches don't collide.
+
+use sqlx::types::chrono::Utc;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The kind of files a new migration is written as
+pub enum Style {
+    /// A Rust module with a `migration()` function, added to the `mod.rs`
+    /// registry listing the migrations
+    Rust,
+    /// A pair of `.up.sql` and `.down.sql` files, loaded by a
+    /// `MigrationDirectory`
+    Sql,
+}
+
+/// Create the files for a new migration named `name`, such as
+/// `add_invoices`, in `src/migrations` for Rust modules or `migrations` for
+/// sql files. Returns the paths of the files created or changed.
+pub fn new_migration(name: &str, style: Style) -> io::Result<Vec<PathBuf>> {
+    let dir = match style {
+        Style::Rust => "src/migrations",
+        Style::Sql => "migrations",
+    };
+    new_migration_in(dir, name, style)
+}
+
+/// Create the files for a new migration named `name` in `dir`, like
+/// `new_migration`. The migration is named `YYYYMMDDHHMMSS_name`, and
+/// existing files are never overwritten.
+pub fn new_migration_in<P: AsRef<Path>>(
+    dir: P,
+    name: &str,
+    style: Style,
+) -> io::Result<Vec<PathBuf>> {
+    if name.is_empty()
+        || !name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "\"{}\" isn't made of lowercase letters, digits and underscores",
+                name
+            ),
+        ));
+    }
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    let name = format!("{}_{}", Utc::now().format("%Y%m%d%H%M%S"), name);
+    match style {
+        Style::Sql => ["up", "down"]
+            .iter()
+            .map(|suffix| create(&dir.join(format!("{}.{}.sql", name, suffix)), ""))
+            .collect(),
+        Style::Rust => {
+            let module = format!("migration_{}", name);
+            let path = dir.join(format!("{}.rs", module));
+            let registry = dir.join("mod.rs");
+            let registered = register(&registry, &module)?;
+            create(&path, &module_source(&name))?;
+            fs::write(&registry, registered)?;
+            Ok(vec![path, registry])
+        }
+    }
+}
+
+fn create(path: &Path, contents: &str) -> io::Result<PathBuf> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(contents.as_bytes())?;
+    Ok(path.to_owned())
+}
+
+fn module_source(name: &str) -> String {
+    format!(
+        r##"use sqlx_simple_migrator::Migration;
+
+pub fn migration() -> Migration {{
+    Migration::new("{}")
+        .with_up(
+            r#"
+        "#,
+        )
+        .with_down(
+            r#"
+        "#,
+        )
+}}
+"##,
+        name
+    )
+}
+
+/// Returns the registry at `path` with `module` added, or a new registry if
+/// it doesn't exist. The module is declared after the last `pub mod` and its
+/// migration is added to the end of the `vec![]` returned by `migrations()`.
+fn register(path: &Path, module: &str) -> io::Result<String> {
+    let registry = match fs::read_to_string(path) {
+        Ok(registry) => registry,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok(format!(
+                "use sqlx_simple_migrator::Migration;\n\n\
+                 pub mod {module};\n\n\
+                 pub fn migrations() -> Vec<Migration> {{\n    \
+                     vec![\n        \
+                         {module}::migration(),\n    \
+                     ]\n\
+                 }}\n",
+                module = module
+            ));
+        }
+        Err(err) => return Err(err),
+    };
+    let mut lines = registry.lines().map(str::to_owned).collect::<Vec<_>>();
+    let end = lines
+        .iter()
+        .rposition(|line| line.trim() == "]")
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("\"{}\" has no vec![] of migrations", path.display()),
+            )
+        })?;
+    let indent = lines[end].len() - lines[end].trim_start().len();
+    lines.insert(
+        end,
+        format!("{}    {}::migration(),", " ".repeat(indent), module),
+    );
+    let declaration = format!("pub mod {};", module);
+    match lines.iter().rposition(|line| line.starts_with("pub mod ")) {
+        Some(last) => lines.insert(last + 1, declaration),
+        None => {
+            let uses = lines
+                .iter()
+                .rposition(|line| line.starts_with("use "))
+                .map_or(0, |last| last + 1);
+            lines.insert(uses, String::new());
+            lines.insert(uses + 1, declaration);
+        }
+    }
+    let mut registry = lines.join("\n");
+    registry.push('\n');
+    Ok(registry)
+}
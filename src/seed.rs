@@ -0,0 +1,72 @@
+use super::{Migration, MigrationBackend, MigrationError, MigrationReport, Migrator};
+use sqlx::Acquire;
+
+/// The table that records which seeds have been applied
+const SEEDS_TABLE: &str = "seeds";
+
+#[derive(Default, Clone, Debug)]
+/// Reference data, such as countries, plans or feature flags, that is applied
+/// once and tracked separately from the schema migrations
+pub struct Seed {
+    pub name: String,
+    pub up: Vec<String>,
+    /// The environments the seed is applied in. When empty, the seed is
+    /// applied in every environment.
+    pub environments: Vec<String>,
+}
+
+impl Seed {
+    /// Create a new seed. The name must be unique among the seeds
+    pub fn new(name: &str) -> Self {
+        Seed {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a sql statement that is performed when applying the seed
+    pub fn with_up(mut self, up: &str) -> Self {
+        self.up.push(up.to_owned());
+        self
+    }
+
+    /// Only apply the seed in `environment`. Can be called more than once to
+    /// apply the seed in several environments.
+    pub fn environment(mut self, environment: &str) -> Self {
+        self.environments.push(environment.to_owned());
+        self
+    }
+
+    /// Returns true if the seed is applied in `environment`
+    pub fn applies_to(&self, environment: &str) -> bool {
+        self.environments.is_empty() || self.environments.iter().any(|e| e == environment)
+    }
+
+    /// Apply the seeds for `environment` that haven't been applied yet, in
+    /// the order they're supplied, recording them in the `seeds` table. The
+    /// schema migrations should be run first.
+    pub async fn run_seeds<'a, A>(
+        executor: A,
+        seeds: Vec<Seed>,
+        environment: &str,
+    ) -> Result<MigrationReport, MigrationError>
+    where
+        A: Acquire<'a>,
+        A::Database: MigrationBackend,
+    {
+        let migrations = seeds
+            .into_iter()
+            .filter(|seed| seed.applies_to(environment))
+            .map(|seed| Migration {
+                name: seed.name,
+                up: seed.up,
+                ..Default::default()
+            })
+            .collect();
+        Migrator::new(executor)
+            .table_name(SEEDS_TABLE)
+            .with_migrations(migrations)
+            .run()
+            .await
+    }
+}
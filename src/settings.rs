@@ -0,0 +1,266 @@
+use super::{DebugModes, Environment, MigrationError, MigratorConfig, Policy};
+use std::{path::Path, str::FromStr, time::Duration};
+
+/// The prefix of the environment variables read by `MigratorConfig::from_env`
+const ENV_PREFIX: &str = "SIMPLE_MIGRATOR_";
+
+/// The settings that can be loaded from the environment or a file
+const KEYS: &[&str] = &[
+    "table_name",
+    "schema",
+    "lock_key",
+    "statement_timeout",
+    "lock_timeout",
+    "retries",
+    "retry_backoff",
+    "checksum_mismatch",
+    "out_of_order",
+    "unknown_applied",
+    "dangerous_statements",
+    "debug_modes",
+    "forbid_down",
+    "require_down",
+    "strict_history",
+    "drop_extensions",
+    "analyze_touched_tables",
+    "environment",
+    "hostname",
+    "app_version",
+    "build_id",
+    "notify_channel",
+    "follower_timeout",
+    "follower_poll_interval",
+    "migrations_root",
+    "search_path",
+    "tracked_schema",
+];
+
+impl MigratorConfig {
+    /// Load the configuration from environment variables named after the
+    /// settings with a `SIMPLE_MIGRATOR_` prefix, such as
+    /// `SIMPLE_MIGRATOR_LOCK_TIMEOUT=5s` or `SIMPLE_MIGRATOR_FORBID_DOWN=true`.
+    /// Settings without a variable keep their defaults.
+    pub fn from_env() -> Result<Self, MigrationError> {
+        let mut config = MigratorConfig::default();
+        for key in KEYS {
+            if let Ok(value) = std::env::var(format!("{}{}", ENV_PREFIX, key.to_uppercase())) {
+                config.set(key, &value).map_err(MigrationError::Config)?;
+            }
+        }
+        Ok(config)
+    }
+
+    /// Load the configuration from a file of `name = value` settings, such as
+    /// `lock_timeout = "5s"` or `forbid_down = true`. The format is a minimal
+    /// subset of TOML without tables, arrays or multi-line strings. Settings
+    /// missing from the file keep their defaults, and unknown settings return
+    /// an error.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, MigrationError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| MigrationError::Config(format!("{}: {}", path.display(), err)))?;
+        let mut config = MigratorConfig::default();
+        for (line, text) in contents.lines().enumerate() {
+            let invalid = |reason: &str| {
+                MigrationError::Config(format!("{}:{}: {}", path.display(), line + 1, reason))
+            };
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let (key, value) = text
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `name = value`"))?;
+            let value = setting_value(value.trim()).ok_or_else(|| invalid("invalid value"))?;
+            config
+                .set(key.trim(), &value)
+                .map_err(|reason| invalid(&reason))?;
+        }
+        Ok(config)
+    }
+
+    /// Sets the setting `key` from its textual `value`
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid_value = || invalid(key, value);
+        let duration = |value: &str| parse_duration(value).ok_or_else(invalid_value);
+        let policy = |value: &str| match value {
+            "error" => Ok(Policy::Error),
+            "warn" => Ok(Policy::Warn),
+            "ignore" => Ok(Policy::Ignore),
+            _ => Err(invalid_value()),
+        };
+        match key {
+            "table_name" => self.table_name = value.to_owned(),
+            "schema" => self.schema = Some(value.to_owned()),
+            "lock_key" => self.lock_key = Some(parse(key, value)?),
+            "statement_timeout" => self.statement_timeout = Some(duration(value)?),
+            "lock_timeout" => self.lock_timeout = Some(duration(value)?),
+            "retries" => self.retries = parse(key, value)?,
+            "retry_backoff" => self.retry_backoff = duration(value)?,
+            "checksum_mismatch" => self.checksum_mismatch = policy(value)?,
+            "out_of_order" => self.out_of_order = policy(value)?,
+            "unknown_applied" => self.unknown_applied = policy(value)?,
+            "dangerous_statements" => self.dangerous_statements = policy(value)?,
+            "debug_modes" => {
+                self.debug_modes = match value {
+                    "execute" => DebugModes::Execute,
+                    "stable" => DebugModes::Stable,
+                    "error" => DebugModes::Error,
+                    _ => return Err(invalid_value()),
+                }
+            }
+            "forbid_down" => self.forbid_down = parse(key, value)?,
+            "require_down" => self.require_down = parse(key, value)?,
+            "strict_history" => self.strict_history = parse(key, value)?,
+            "drop_extensions" => self.drop_extensions = parse(key, value)?,
+            "analyze_touched_tables" => self.analyze_touched_tables = parse(key, value)?,
+            "environment" => self.environment = Some(Environment::from(value)),
+            "hostname" => self.hostname = Some(value.to_owned()),
+            "app_version" => self.app_version = Some(value.to_owned()),
+            "build_id" => self.build_id = Some(value.to_owned()),
+            "notify_channel" => self.notify_channel = Some(value.to_owned()),
+            "follower_timeout" => self.follower_timeout = Some(duration(value)?),
+            "follower_poll_interval" => self.follower_poll_interval = duration(value)?,
+            "migrations_root" => self.migrations_root = Some(value.into()),
+            "search_path" => self.search_path = Some(value.to_owned()),
+            "tracked_schema" => self.tracked_schema = Some(value.to_owned()),
+            _ => return Err(format!("unknown setting {}", key)),
+        }
+        Ok(())
+    }
+}
+
+fn invalid(key: &str, value: &str) -> String {
+    format!("invalid value for {}: {:?}", key, value)
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| invalid(key, value))
+}
+
+/// Parses a duration such as `500ms`, `5s`, `10m` or `1h`. A number without
+/// a unit is a number of seconds. Returns `None` for a duration that
+/// overflows.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount = amount.parse::<u64>().ok()?;
+    match unit.trim() {
+        "ms" => Some(Duration::from_millis(amount)),
+        "" | "s" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_secs(amount.checked_mul(60)?)),
+        "h" => Some(Duration::from_secs(amount.checked_mul(60 * 60)?)),
+        _ => None,
+    }
+}
+
+/// Returns the text of a setting's value, without a trailing comment. The
+/// value is either a bare word, such as a number or boolean, or a string in
+/// single or double quotes. Double-quoted strings support the `\n`, `\t`,
+/// `\"` and `\\` escapes.
+fn setting_value(value: &str) -> Option<String> {
+    let mut chars = value.chars();
+    let quote = match value.chars().next()? {
+        quote @ ('"' | '\'') => quote,
+        _ => {
+            let bare = value.split('#').next()?.trim();
+            return (!bare.is_empty()).then(|| bare.to_owned());
+        }
+    };
+    chars.next();
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c == quote => {
+                let rest = chars.as_str().trim();
+                return (rest.is_empty() || rest.starts_with('#')).then_some(text);
+            }
+            '\\' if quote == '"' => text.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                c @ ('"' | '\\') => c,
+                _ => return None,
+            }),
+            c => text.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("5"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_duration("5s"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_duration("10m"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("1d"), None);
+        assert_eq!(parse_duration("s"), None);
+        assert_eq!(parse_duration("-1s"), None);
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert_eq!(
+            parse_duration(&u64::MAX.to_string()),
+            Some(Duration::from_secs(u64::MAX))
+        );
+        assert_eq!(parse_duration(&format!("{}m", u64::MAX / 2)), None);
+        assert_eq!(parse_duration(&format!("{}h", u64::MAX / 60)), None);
+    }
+
+    #[test]
+    fn reads_setting_values() {
+        assert_eq!(setting_value("true").as_deref(), Some("true"));
+        assert_eq!(
+            setting_value("8675309 # the lock").as_deref(),
+            Some("8675309")
+        );
+        assert_eq!(setting_value(r#""5s""#).as_deref(), Some("5s"));
+        assert_eq!(setting_value("'a # b' # comment").as_deref(), Some("a # b"));
+        assert_eq!(
+            setting_value(r#""a\tb\"c\"""#).as_deref(),
+            Some("a\tb\"c\"")
+        );
+        assert_eq!(
+            setting_value(r"'C:\migrations'").as_deref(),
+            Some(r"C:\migrations")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_setting_values() {
+        assert_eq!(setting_value(""), None);
+        assert_eq!(setting_value("# comment"), None);
+        assert_eq!(setting_value(r#""unterminated"#), None);
+        assert_eq!(setting_value(r#""a" b"#), None);
+        assert_eq!(setting_value(r#""\q""#), None);
+    }
+
+    #[test]
+    fn every_key_is_a_setting() {
+        for key in KEYS {
+            let result = MigratorConfig::default().set(key, "");
+            assert_ne!(result, Err(format!("unknown setting {}", key)));
+        }
+        assert!(MigratorConfig::default().set("unknown", "").is_err());
+    }
+
+    #[test]
+    fn sets_settings_from_text() {
+        let mut config = MigratorConfig::default();
+        config.set("lock_timeout", "5s").unwrap();
+        config.set("checksum_mismatch", "warn").unwrap();
+        config.set("forbid_down", "true").unwrap();
+        assert_eq!(config.lock_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(config.checksum_mismatch, Policy::Warn);
+        assert!(config.forbid_down);
+        assert!(config.set("retries", "many").is_err());
+    }
+}
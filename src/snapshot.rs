@@ -0,0 +1,173 @@
+use super::{logging, Migration, MigrationBackend, MigrationError, MigratorConfig};
+use std::collections::HashSet;
+
+impl Migration {
+    /// Consolidates `migrations` into a single snapshot migration named
+    /// `name`. The up statements and Rust code are executed in order, and the
+    /// down statements in reverse. Databases that have applied every one of
+    /// `migrations` record the snapshot as performed instead of executing it.
+    /// Conditions and `continue_on_error` aren't carried over.
+    pub fn squash(name: &str, migrations: &[Migration]) -> Self {
+        let mut snapshot = Migration::new(name);
+        // The down statements of earlier migrations come after those of later
+        // ones, so they are counted from the end
+        let mut down_after = 0;
+        for migration in migrations {
+            let offset = snapshot.up.len();
+            snapshot.up_files.extend(
+                migration
+                    .up_files
+                    .iter()
+                    .map(|(index, path)| (index + offset, path.clone())),
+            );
+            snapshot.down_files.extend(
+                migration
+                    .down_files
+                    .iter()
+                    .map(|(from_end, path)| (from_end + down_after, path.clone())),
+            );
+            down_after += migration.down.len();
+            snapshot
+                .up_fns
+                .extend(migration.up_fns.iter().cloned().map(|mut function| {
+                    function.position += offset;
+                    function
+                }));
+            snapshot.up.extend(migration.up.iter().cloned());
+            snapshot
+                .expectations
+                .extend(migration.expectations.iter().cloned());
+            snapshot
+                .extensions
+                .extend(migration.extensions.iter().cloned());
+            snapshot.no_transaction |= migration.no_transaction;
+            snapshot.transaction_per_statement |= migration.transaction_per_statement;
+            snapshot.irreversible |= migration.irreversible;
+            snapshot.version = snapshot.version.max(migration.version);
+            snapshot.replaces.extend(migration.replaces.iter().cloned());
+            snapshot.replaces.push(migration.name.clone());
+        }
+        for migration in migrations.iter().rev() {
+            snapshot.down.extend(migration.down.iter().cloned());
+        }
+        snapshot
+    }
+
+    /// Marks this migration as equivalent to the migrations named `names`, so
+    /// that databases that have applied all of them record this migration as
+    /// performed instead of executing it
+    pub fn replaces(mut self, names: &[&str]) -> Self {
+        self.replaces
+            .extend(names.iter().map(|&name| name.to_owned()));
+        self
+    }
+}
+
+/// Records each pending snapshot whose replaced migrations have all been
+/// performed, returning the updated names of the performed migrations
+pub(crate) async fn adopt<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    mut performed_migrations: HashSet<String>,
+    config: &MigratorConfig,
+) -> Result<HashSet<String>, MigrationError> {
+    for migration in adoptable(migrations, &performed_migrations)? {
+        migration.record::<DB>(conn, config, None).await?;
+        logging::snapshot_adopted(&migration.name);
+        performed_migrations.insert(migration.name.clone());
+    }
+    Ok(performed_migrations)
+}
+
+/// Returns the pending snapshots whose replaced migrations have all been
+/// performed
+pub(crate) fn adoptable<'m>(
+    migrations: &'m [Migration],
+    performed_migrations: &HashSet<String>,
+) -> Result<Vec<&'m Migration>, MigrationError> {
+    let mut adoptable = Vec::new();
+    for migration in migrations
+        .iter()
+        .filter(|m| !m.replaces.is_empty() && !performed_migrations.contains(&m.name))
+    {
+        let missing = migration
+            .replaces
+            .iter()
+            .filter(|name| !performed_migrations.contains(name.as_str()))
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            adoptable.push(migration);
+        } else if missing.len() < migration.replaces.len() {
+            return Err(MigrationError::PartialSnapshot {
+                migration: migration.name.clone(),
+                missing: missing[0].clone(),
+            });
+        }
+    }
+    Ok(adoptable)
+}
+
+/// Returns the names of the migrations replaced by `migrations`
+pub(crate) fn replaced(migrations: &[Migration]) -> HashSet<&str> {
+    migrations
+        .iter()
+        .flat_map(|m| m.replaces.iter().map(String::as_str))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::load_files;
+    use std::fs;
+
+    #[test]
+    fn squashes_statements_read_from_files() {
+        let root =
+            std::env::temp_dir().join(format!("simple-migrator-squash-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        for (file, sql) in [
+            ("users.up.sql", "CREATE TABLE users (id BIGINT)"),
+            ("users.down.sql", "DROP TABLE users"),
+            ("orders.up.sql", "CREATE TABLE orders (id BIGINT)"),
+            ("orders.down.sql", "DROP TABLE orders"),
+        ] {
+            fs::write(root.join(file), sql).unwrap();
+        }
+
+        let mut snapshot = vec![Migration::squash(
+            "snapshot",
+            &[
+                Migration::new("users")
+                    .with_up_file("users.up.sql")
+                    .with_up("CREATE INDEX users_id ON users (id)")
+                    .with_down_file("users.down.sql")
+                    .with_down("DROP INDEX users_id"),
+                Migration::new("orders")
+                    .with_up_file("orders.up.sql")
+                    .with_down_file("orders.down.sql"),
+            ],
+        )];
+        let config = MigratorConfig::default().migrations_root(&root);
+        let result = load_files(&mut snapshot, &config);
+        fs::remove_dir_all(&root).unwrap();
+        result.unwrap();
+
+        assert_eq!(
+            snapshot[0].up,
+            vec![
+                "CREATE TABLE users (id BIGINT)",
+                "CREATE INDEX users_id ON users (id)",
+                "CREATE TABLE orders (id BIGINT)",
+            ]
+        );
+        assert_eq!(
+            snapshot[0].down,
+            vec![
+                "DROP TABLE orders",
+                "DROP INDEX users_id",
+                "DROP TABLE users"
+            ]
+        );
+    }
+}
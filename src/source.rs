@@ -0,0 +1,386 @@
+use super::{Migration, MigrationError, MigratorConfig};
+use futures_core::future::BoxFuture;
+use sqlx::error::BoxDynError;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Supplies the migrations to execute, such as from a directory, an object
+/// store, or another crate. `Vec<Migration>`, including the one expanded by
+/// `include_migrations!`, and `MigrationDirectory` are sources.
+pub trait MigrationSource: Send + Sync {
+    /// Loads the migrations, in the order they should be executed
+    fn migrations(&self) -> BoxFuture<'_, Result<Vec<Migration>, BoxDynError>>;
+}
+
+impl MigrationSource for Vec<Migration> {
+    fn migrations(&self) -> BoxFuture<'_, Result<Vec<Migration>, BoxDynError>> {
+        let migrations = self.clone();
+        Box::pin(async move { Ok(migrations) })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A directory of sql files to load migrations from. Each migration is made up
+/// of a `NNN_name.up.sql` file and an optional `NNN_name.down.sql` file, where
+/// `NNN` is a number that determines the order migrations are executed in.
+pub struct MigrationDirectory {
+    path: PathBuf,
+}
+
+#[derive(Error, Debug)]
+/// An error loading migrations from a `MigrationDirectory`
+pub enum MigrationSourceError {
+    #[error("Error reading \"{}\": {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("\"{}\" is not named NNN_name.up.sql, NNN_name.down.sql, V<version>__name.sql or R__name.sql", .0.display())]
+    InvalidFileName(PathBuf),
+    #[error("Migration number {0} is used by both \"{1}\" and \"{2}\"")]
+    DuplicateNumber(String, String, String),
+    #[error("\"{0}\" has a down file but no up file")]
+    MissingUp(String),
+}
+
+#[derive(Default)]
+struct MigrationFiles {
+    name: String,
+    up: Option<String>,
+    down: Option<String>,
+}
+
+impl MigrationDirectory {
+    /// Create a source that scans `path` for migration files
+    pub fn from_dir<P: AsRef<Path>>(path: P) -> Self {
+        MigrationDirectory {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    /// Read the migrations from the directory, ordered by their version. The
+    /// name of each migration is its file name without the `.up.sql`,
+    /// `.down.sql` or `.sql` suffix. Besides `NNN_name.up.sql` and
+    /// `NNN_name.down.sql`, sqlx-cli's `NNN_name.sql` and Flyway's
+    /// `V1_2__name.sql` and `U1_2__name.sql` files are recognized, and
+    /// Flyway's `R__name.sql` files are loaded as repeatable migrations after
+    /// the others, ordered by name. Other files are ignored.
+    pub fn migrations(&self) -> Result<Vec<Migration>, MigrationSourceError> {
+        let io_error = |source| MigrationSourceError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        let mut files = BTreeMap::<Vec<u64>, MigrationFiles>::new();
+        let mut repeatable = BTreeMap::<String, String>::new();
+        for entry in fs::read_dir(&self.path).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(file_name) => file_name,
+                None => continue,
+            };
+            let file = match parse_file_name(file_name) {
+                Some(Ok(file)) => file,
+                Some(Err(())) => return Err(MigrationSourceError::InvalidFileName(path.clone())),
+                None => continue,
+            };
+            let sql = fs::read_to_string(&path).map_err(|source| MigrationSourceError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            if file.repeatable {
+                repeatable.insert(file.name, sql);
+                continue;
+            }
+
+            let migration = files.entry(file.version).or_default();
+            if migration.name.is_empty() {
+                migration.name = file.name;
+            } else if migration.name != file.name {
+                return Err(MigrationSourceError::DuplicateNumber(
+                    file.version_text,
+                    migration.name.clone(),
+                    file.name,
+                ));
+            }
+            if file.is_up {
+                migration.up = Some(sql);
+            } else {
+                migration.down = Some(sql);
+            }
+        }
+
+        files
+            .into_values()
+            .map(|files| {
+                let MigrationFiles { name, up, down } = files;
+                let up = up.ok_or_else(|| MigrationSourceError::MissingUp(name.clone()))?;
+                let mut migration = Migration::new(&name).with_up(&up);
+                if let Some(down) = down {
+                    migration = migration.with_down(&down);
+                }
+                Ok(migration)
+            })
+            .chain(
+                repeatable
+                    .into_iter()
+                    .map(|(name, sql)| Ok(Migration::repeatable(&name, &sql))),
+            )
+            .collect()
+    }
+}
+
+impl MigrationSource for MigrationDirectory {
+    fn migrations(&self) -> BoxFuture<'_, Result<Vec<Migration>, BoxDynError>> {
+        Box::pin(async move { Ok(MigrationDirectory::migrations(self)?) })
+    }
+}
+
+impl Migration {
+    /// Add an "Up" statement, or several, read from the file at `path` when
+    /// the migration is run. A relative path is resolved against the
+    /// configured `migrations_root`.
+    pub fn with_up_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.up_files
+            .push((self.up.len(), path.as_ref().to_owned()));
+        self.up.push(String::new());
+        self
+    }
+
+    /// Add a "Down" statement, or several, read from the file at `path` when
+    /// the migration is run, like `with_up_file`
+    pub fn with_down_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.down_files
+            .push((self.down.len(), path.as_ref().to_owned()));
+        self.down.insert(0, String::new());
+        self
+    }
+}
+
+/// Reads the files added by `with_up_file` and `with_down_file` into the
+/// migrations' statements
+pub(crate) fn load_files(
+    migrations: &mut [Migration],
+    config: &MigratorConfig,
+) -> Result<(), MigrationError> {
+    let read = |path: &Path| {
+        let path = match &config.migrations_root {
+            Some(root) => root.join(path),
+            None => path.to_owned(),
+        };
+        fs::read_to_string(&path).map_err(|source| {
+            MigrationError::Source(Box::new(MigrationSourceError::Io { path, source }))
+        })
+    };
+    for migration in migrations.iter_mut() {
+        for (index, path) in std::mem::take(&mut migration.up_files) {
+            migration.up[index] = read(&path)?;
+        }
+        for (from_end, path) in std::mem::take(&mut migration.down_files) {
+            let index = migration.down.len() - 1 - from_end;
+            migration.down[index] = read(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// A migration file recognized by its name
+struct MigrationFile {
+    version: Vec<u64>,
+    version_text: String,
+    name: String,
+    is_up: bool,
+    repeatable: bool,
+}
+
+/// Parses a migration file name, returning `None` for files that aren't
+/// migrations and an error for files that look like migrations but are
+/// misnamed
+fn parse_file_name(file_name: &str) -> Option<Result<MigrationFile, ()>> {
+    let (stem, is_up) = if let Some(stem) = file_name.strip_suffix(".up.sql") {
+        (stem, Some(true))
+    } else if let Some(stem) = file_name.strip_suffix(".down.sql") {
+        (stem, Some(false))
+    } else {
+        (file_name.strip_suffix(".sql")?, None)
+    };
+    let file = match is_up {
+        Some(is_up) => parse_number(stem).map(|(number, version_text)| MigrationFile {
+            version: vec![number],
+            version_text,
+            name: stem.to_owned(),
+            is_up,
+            repeatable: false,
+        }),
+        None => match stem.split_once("__") {
+            Some((version, description)) if version.starts_with(['V', 'U']) => {
+                let version_text = &version[1..];
+                parse_version(version_text)
+                    .filter(|_| !description.is_empty())
+                    .map(|version| MigrationFile {
+                        version,
+                        version_text: version_text.to_owned(),
+                        // Undo files belong to the versioned migration
+                        name: format!("V{}__{}", version_text, description),
+                        is_up: stem.starts_with('V'),
+                        repeatable: false,
+                    })
+            }
+            Some(("R", description)) => (!description.is_empty()).then(|| MigrationFile {
+                version: Vec::new(),
+                version_text: String::new(),
+                name: stem.to_owned(),
+                is_up: true,
+                repeatable: true,
+            }),
+            // Files that are neither a Flyway nor sqlx-cli migration, such as
+            // a schema dump, are ignored
+            _ => {
+                let (number, version_text) = parse_number(stem)?;
+                Some(MigrationFile {
+                    version: vec![number],
+                    version_text,
+                    name: stem.to_owned(),
+                    is_up: true,
+                    repeatable: false,
+                })
+            }
+        },
+    };
+    Some(file.ok_or(()))
+}
+
+/// Parses the `NNN` from `NNN_name`
+fn parse_number(name: &str) -> Option<(u64, String)> {
+    let (number, name) = name.split_at(name.find('_')?);
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) || name.len() <= 1 {
+        return None;
+    }
+    Some((number.parse().ok()?, number.to_owned()))
+}
+
+/// Parses a Flyway version such as `1_2` or `1.2`
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .split(['.', '_'])
+        .map(|part| {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            part.parse().ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The version, name, whether it's an up file and whether it's repeatable
+    type Parsed = (Vec<u64>, String, bool, bool);
+
+    fn parsed(file_name: &str) -> Option<Result<Parsed, ()>> {
+        parse_file_name(file_name)
+            .map(|file| file.map(|f| (f.version, f.name, f.is_up, f.repeatable)))
+    }
+
+    fn file(
+        version: &[u64],
+        name: &str,
+        is_up: bool,
+        repeatable: bool,
+    ) -> Option<Result<Parsed, ()>> {
+        Some(Ok((version.to_vec(), name.to_owned(), is_up, repeatable)))
+    }
+
+    #[test]
+    fn parses_up_and_down_files() {
+        assert_eq!(
+            parsed("001_users.up.sql"),
+            file(&[1], "001_users", true, false)
+        );
+        assert_eq!(
+            parsed("001_users.down.sql"),
+            file(&[1], "001_users", false, false)
+        );
+        assert_eq!(parsed("users.up.sql"), Some(Err(())));
+        assert_eq!(parsed("001_.up.sql"), Some(Err(())));
+    }
+
+    #[test]
+    fn parses_sqlx_cli_files() {
+        assert_eq!(
+            parsed("20240101120000_create_users.sql"),
+            file(
+                &[20240101120000],
+                "20240101120000_create_users",
+                true,
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn parses_flyway_files() {
+        assert_eq!(
+            parsed("V1_2__users.sql"),
+            file(&[1, 2], "V1_2__users", true, false)
+        );
+        assert_eq!(
+            parsed("U1.2__users.sql"),
+            file(&[1, 2], "V1.2__users", false, false)
+        );
+        assert_eq!(parsed("R__views.sql"), file(&[], "R__views", true, true));
+        assert_eq!(parsed("V1__.sql"), Some(Err(())));
+        assert_eq!(parsed("Vx__users.sql"), Some(Err(())));
+        assert_eq!(parsed("R__.sql"), Some(Err(())));
+    }
+
+    #[test]
+    fn ignores_other_files() {
+        assert_eq!(parsed("README.md"), None);
+        assert_eq!(parsed("schema.sql"), None);
+    }
+
+    #[test]
+    fn loads_files_into_statements() {
+        let root =
+            std::env::temp_dir().join(format!("simple-migrator-load-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("up.sql"), "CREATE TABLE users (id BIGINT)").unwrap();
+        fs::write(root.join("down.sql"), "DROP TABLE users").unwrap();
+
+        let mut migrations = vec![Migration::new("users")
+            .with_up_file("up.sql")
+            .with_up("CREATE INDEX users_id ON users (id)")
+            .with_down("DROP INDEX users_id")
+            .with_down_file("down.sql")];
+        let config = MigratorConfig::default().migrations_root(&root);
+        let result = load_files(&mut migrations, &config);
+        fs::remove_dir_all(&root).unwrap();
+        result.unwrap();
+
+        let migration = &migrations[0];
+        assert_eq!(
+            migration.up,
+            vec![
+                "CREATE TABLE users (id BIGINT)",
+                "CREATE INDEX users_id ON users (id)"
+            ]
+        );
+        assert_eq!(
+            migration.down,
+            vec!["DROP TABLE users", "DROP INDEX users_id"]
+        );
+        assert!(migration.up_files.is_empty() && migration.down_files.is_empty());
+    }
+
+    #[test]
+    fn returns_an_error_for_missing_files() {
+        let mut migrations = vec![Migration::new("missing").with_up_file("/nonexistent/up.sql")];
+        match load_files(&mut migrations, &MigratorConfig::default()) {
+            Err(MigrationError::Source(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use super::{
+    file_name, is_builtin, migration_6_schema, read_history, render, snapshot, tracked, Migration,
+    MigrationBackend, MigrationError, MigratorConfig,
+};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    Connection,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter, Write},
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Which migrations have been applied to a database, compared against the
+/// migrations supplied in code. The crate's own bookkeeping migrations are
+/// not included.
+pub struct MigrationStatus {
+    /// The migrations recorded as performed, in the order they were executed
+    pub applied: Vec<AppliedMigration>,
+    /// The supplied migrations that haven't been performed, in the order they
+    /// would be executed
+    pub pending: Vec<String>,
+    /// The migrations recorded as performed that weren't supplied
+    pub unknown: Vec<String>,
+    /// The applied migrations whose up statements have changed since they
+    /// were applied. Changed managed migrations are pending instead.
+    pub drifted: Vec<String>,
+}
+
+impl MigrationStatus {
+    /// Returns the state of the applied migration `name`
+    fn state(&self, name: &str) -> &'static str {
+        if self.drifted.iter().any(|n| n == name) {
+            "drifted"
+        } else if self.unknown.iter().any(|n| n == name) {
+            "unknown"
+        } else {
+            "applied"
+        }
+    }
+
+    /// Renders the status as a JSON object with `applied`, `pending`,
+    /// `unknown` and `drifted` fields, for tools parsing a CLI's output. Each
+    /// applied migration has a `name`, `state`, `executed_at` and
+    /// `description`.
+    pub fn to_json(&self) -> String {
+        let names = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| json_string(name))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let applied = self
+            .applied
+            .iter()
+            .map(|m| {
+                format!(
+                    r#"{{"name":{},"state":"{}","executed_at":"{}","description":{}}}"#,
+                    json_string(&m.name),
+                    self.state(&m.name),
+                    m.executed_at.format("%Y-%m-%dT%H:%M:%SZ"),
+                    m.description
+                        .as_deref()
+                        .map_or_else(|| String::from("null"), json_string)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"applied":[{}],"pending":[{}],"unknown":[{}],"drifted":[{}]}}"#,
+            applied,
+            names(&self.pending),
+            names(&self.unknown),
+            names(&self.drifted)
+        )
+    }
+}
+
+/// Renders the status as a table with a row for each applied migration,
+/// followed by each pending migration
+impl Display for MigrationStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let width = self
+            .applied
+            .iter()
+            .map(|m| m.name.as_str())
+            .chain(self.pending.iter().map(String::as_str))
+            .map(str::len)
+            .fold("NAME".len(), usize::max);
+        write!(
+            f,
+            "{:<8} {:<width$} {:<20} DESCRIPTION",
+            "STATE",
+            "NAME",
+            "EXECUTED AT",
+            width = width
+        )?;
+        for m in self.applied.iter() {
+            let row = format!(
+                "{:<8} {:<width$} {:<20} {}",
+                self.state(&m.name),
+                m.name,
+                m.executed_at.format("%Y-%m-%d %H:%M:%S"),
+                m.description.as_deref().unwrap_or_default(),
+                width = width
+            );
+            write!(f, "\n{}", row.trim_end())?;
+        }
+        for name in self.pending.iter() {
+            write!(f, "\n{:<8} {}", "pending", name)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// A migration recorded as performed
+pub struct AppliedMigration {
+    pub name: String,
+    pub executed_at: DateTime<Utc>,
+    /// The description recorded when the migration was performed, or the
+    /// supplied migration's description if none was recorded
+    pub description: Option<String>,
+}
+
+/// Compares the applied migrations against `migrations`. The query runs in
+/// its own transaction so that a missing `migrations` table doesn't abort a
+/// transaction the status is being read within.
+pub(crate) async fn status<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    config: &MigratorConfig,
+) -> Result<MigrationStatus, MigrationError> {
+    let table = config.qualified_table_name();
+    let mut tx = migration_try!(
+        conn.begin().await,
+        Transaction {
+            migration: None,
+            sql: String::from("BEGIN"),
+        }
+    );
+    let mut recorded = read_history::<DB, _>(
+        DB::applied_migrations(&mut tx, &table).await,
+        render(DB::SELECT_APPLIED_MIGRATIONS, &table),
+        config,
+    )?;
+    migration_try!(
+        tx.rollback().await,
+        Transaction {
+            migration: None,
+            sql: String::from("ROLLBACK"),
+        }
+    );
+
+    // Records that would be renamed are reported under their normalized names
+    let recorded_names = recorded
+        .iter()
+        .map(|(_, name, ..)| name.clone())
+        .collect::<HashSet<_>>();
+    let renamed = file_name::legacy_names(migrations, &recorded_names)
+        .into_iter()
+        .map(|(legacy, m)| (legacy, m.name.clone()))
+        .collect::<HashMap<_, _>>();
+    for (_, name, ..) in recorded.iter_mut() {
+        if let Some(normalized) = renamed.get(name) {
+            *name = normalized.clone();
+        }
+    }
+
+    let mut supplied = migrations
+        .iter()
+        .map(|m| m.name.as_str())
+        .collect::<HashSet<_>>();
+    supplied.extend(snapshot::replaced(migrations));
+    let mut applied = Vec::new();
+    let mut unknown = Vec::new();
+    for (schema, name, executed_at, description) in recorded {
+        if is_builtin(&name) || !tracked(config, &schema, &name) {
+            continue;
+        }
+        if !supplied.contains(name.as_str()) {
+            unknown.push(name.clone());
+        }
+        let description = description.or_else(|| {
+            migrations
+                .iter()
+                .find(|m| m.name == name)
+                .and_then(|m| m.description.clone())
+        });
+        applied.push(AppliedMigration {
+            name,
+            executed_at,
+            description,
+        });
+    }
+
+    let mut performed = applied
+        .iter()
+        .map(|m| m.name.clone())
+        .collect::<HashSet<_>>();
+    // Snapshots that would be recorded as performed aren't pending
+    let adopted = snapshot::adoptable(migrations, &performed)?
+        .into_iter()
+        .map(|m| m.name.clone())
+        .collect::<Vec<_>>();
+    performed.extend(adopted);
+    let mut pending = migrations
+        .iter()
+        .filter(|m| !is_builtin(&m.name) && !performed.contains(&m.name))
+        .map(|m| m.name.clone())
+        .collect::<Vec<_>>();
+
+    let mut drifted = Vec::new();
+    // Checksums are only looked up by schema once the table has been upgraded.
+    // Records that would be renamed and snapshots that would be adopted don't
+    // have a row under their names yet.
+    if recorded_names.contains(migration_6_schema::NAME) {
+        for migration in migrations.iter().filter(|m| {
+            !is_builtin(&m.name) && performed.contains(&m.name) && recorded_names.contains(&m.name)
+        }) {
+            let applied = migration_try!(
+                DB::migration_checksum(conn, &table, config.recorded_schema(), &migration.name)
+                    .await,
+                Bookkeeping {
+                    migration: Some(migration.name.clone()),
+                    sql: render(DB::SELECT_CHECKSUM, &table),
+                }
+            );
+            match applied {
+                Some(applied) if applied != migration.checksum() => {
+                    // Managed migrations are performed again by the next run
+                    if migration.managed {
+                        pending.push(migration.name.clone());
+                    } else {
+                        drifted.push(migration.name.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(MigrationStatus {
+        applied,
+        pending,
+        unknown,
+        drifted,
+    })
+}
+
+/// Checks that every supplied migration selected by the tag filter has been
+/// applied, that every applied migration was supplied, and that no applied
+/// migration has changed since it was applied. Nothing is modified.
+pub(crate) async fn check<DB: MigrationBackend>(
+    conn: &mut DB::Connection,
+    migrations: &[Migration],
+    config: &MigratorConfig,
+) -> Result<(), MigrationError> {
+    let status = status::<DB>(conn, migrations, config).await?;
+    let pending = migrations
+        .iter()
+        .filter(|m| {
+            let changed = m.managed && status.applied.iter().any(|a| a.name == m.name);
+            status.pending.contains(&m.name) && (changed || config.tag_filter.matches(m))
+        })
+        .map(|m| m.name.clone())
+        .collect::<Vec<_>>();
+    if pending.is_empty() && status.unknown.is_empty() && status.drifted.is_empty() {
+        Ok(())
+    } else {
+        Err(MigrationError::OutOfSync {
+            pending,
+            unknown: status.unknown,
+            changed: status.drifted,
+        })
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,392 @@
+use super::{Migration, MigrationBackend};
+use std::borrow::Cow;
+
+#[derive(Clone, Debug, PartialEq)]
+/// The type of a column created by a `Table`, translated to each database's
+/// closest equivalent
+pub enum ColumnType {
+    SmallInt,
+    Integer,
+    BigInt,
+    /// An auto-incrementing integer
+    Serial,
+    /// An auto-incrementing 64-bit integer
+    BigSerial,
+    Boolean,
+    Real,
+    Double,
+    /// A decimal with the precision and scale given
+    Numeric(u8, u8),
+    Text,
+    Varchar(u32),
+    Bytes,
+    Date,
+    Timestamp,
+    /// A timestamp with a time zone
+    TimestampTz,
+    Uuid,
+    Json,
+    /// A type written out as sql, such as `INET`
+    Custom(String),
+}
+
+/// How a database names column types and quotes identifiers in the
+/// statements generated by a `Table`
+pub trait TableDialect: MigrationBackend {
+    /// The character identifiers are quoted with
+    const IDENTIFIER_QUOTE: char = '"';
+
+    /// Returns the sql type of `column_type`
+    fn column_type(column_type: &ColumnType) -> Cow<'static, str>;
+}
+
+#[derive(Clone, Debug)]
+/// A column of a `Table`
+pub struct Column {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub nullable: bool,
+    /// The sql expression the column defaults to
+    pub default: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+/// A foreign key of a `Table`
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    pub references: String,
+    pub referenced_columns: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+/// Builds the statements creating and dropping a table, with every
+/// identifier quoted
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub primary_key: Vec<String>,
+    pub unique: Vec<Vec<String>>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// Starts building the table `name`, which may be qualified with a schema
+pub fn table(name: &str) -> Table {
+    Table {
+        name: name.to_owned(),
+        columns: Vec::new(),
+        primary_key: Vec::new(),
+        unique: Vec::new(),
+        foreign_keys: Vec::new(),
+    }
+}
+
+impl Table {
+    /// Add a `NOT NULL` column
+    pub fn column(mut self, name: &str, column_type: ColumnType) -> Self {
+        self.columns.push(Column {
+            name: name.to_owned(),
+            column_type,
+            nullable: false,
+            default: None,
+        });
+        self
+    }
+
+    /// Add a column that allows `NULL`
+    pub fn nullable_column(self, name: &str, column_type: ColumnType) -> Self {
+        let mut table = self.column(name, column_type);
+        if let Some(column) = table.columns.last_mut() {
+            column.nullable = true;
+        }
+        table
+    }
+
+    /// Set the default of the most recently added column to the sql
+    /// expression `default`, such as `now()` or `'pending'`
+    pub fn default(mut self, default: &str) -> Self {
+        if let Some(column) = self.columns.last_mut() {
+            column.default = Some(default.to_owned());
+        }
+        self
+    }
+
+    /// Set the columns making up the primary key
+    pub fn primary_key(mut self, columns: &[&str]) -> Self {
+        self.primary_key = columns.iter().map(|&c| c.to_owned()).collect();
+        self
+    }
+
+    /// Add a unique constraint on `columns`
+    pub fn unique(mut self, columns: &[&str]) -> Self {
+        self.unique
+            .push(columns.iter().map(|&c| c.to_owned()).collect());
+        self
+    }
+
+    /// Add a foreign key from `columns` to `referenced_columns` of the table
+    /// `references`
+    pub fn foreign_key(
+        mut self,
+        columns: &[&str],
+        references: &str,
+        referenced_columns: &[&str],
+    ) -> Self {
+        self.foreign_keys.push(ForeignKey {
+            columns: columns.iter().map(|&c| c.to_owned()).collect(),
+            references: references.to_owned(),
+            referenced_columns: referenced_columns.iter().map(|&c| c.to_owned()).collect(),
+        });
+        self
+    }
+
+    /// Returns the `CREATE TABLE` statement
+    pub fn up<DB: TableDialect>(&self) -> String {
+        let list = |columns: &[String]| {
+            columns
+                .iter()
+                .map(|c| quote::<DB>(c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut definitions = self
+            .columns
+            .iter()
+            .map(|column| {
+                let mut definition = format!(
+                    "{} {}",
+                    quote::<DB>(&column.name),
+                    DB::column_type(&column.column_type)
+                );
+                if !column.nullable {
+                    definition.push_str(" NOT NULL");
+                }
+                if let Some(default) = &column.default {
+                    definition.push_str(" DEFAULT ");
+                    definition.push_str(default);
+                }
+                definition
+            })
+            .collect::<Vec<_>>();
+        if !self.primary_key.is_empty() {
+            definitions.push(format!("PRIMARY KEY ({})", list(&self.primary_key)));
+        }
+        for columns in self.unique.iter() {
+            definitions.push(format!("UNIQUE ({})", list(columns)));
+        }
+        for foreign_key in self.foreign_keys.iter() {
+            definitions.push(format!(
+                "FOREIGN KEY ({}) REFERENCES {} ({})",
+                list(&foreign_key.columns),
+                quote::<DB>(&foreign_key.references),
+                list(&foreign_key.referenced_columns)
+            ));
+        }
+        format!(
+            "CREATE TABLE {} (\n    {}\n)",
+            quote::<DB>(&self.name),
+            definitions.join(",\n    ")
+        )
+    }
+
+    /// Returns the `DROP TABLE` statement
+    pub fn down<DB: TableDialect>(&self) -> String {
+        format!("DROP TABLE {}", quote::<DB>(&self.name))
+    }
+}
+
+impl Migration {
+    /// Add the statement creating `table` as an "Up" statement, and the
+    /// statement dropping it as a "Down" statement
+    pub fn with_table<DB: TableDialect>(self, table: &Table) -> Self {
+        self.with_up(&table.up::<DB>())
+            .with_down(&table.down::<DB>())
+    }
+}
+
+/// Quotes each part of a possibly schema-qualified identifier
+fn quote<DB: TableDialect>(identifier: &str) -> String {
+    let quote = DB::IDENTIFIER_QUOTE;
+    identifier
+        .split('.')
+        .map(|part| {
+            let escaped = part.replace(quote, &format!("{}{}", quote, quote));
+            format!("{}{}{}", quote, escaped, quote)
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(feature = "postgres")]
+impl TableDialect for sqlx::Postgres {
+    fn column_type(column_type: &ColumnType) -> Cow<'static, str> {
+        match column_type {
+            ColumnType::SmallInt => "SMALLINT".into(),
+            ColumnType::Integer => "INTEGER".into(),
+            ColumnType::BigInt => "BIGINT".into(),
+            ColumnType::Serial => "SERIAL".into(),
+            ColumnType::BigSerial => "BIGSERIAL".into(),
+            ColumnType::Boolean => "BOOLEAN".into(),
+            ColumnType::Real => "REAL".into(),
+            ColumnType::Double => "DOUBLE PRECISION".into(),
+            ColumnType::Numeric(precision, scale) => {
+                format!("NUMERIC({}, {})", precision, scale).into()
+            }
+            ColumnType::Text => "TEXT".into(),
+            ColumnType::Varchar(length) => format!("VARCHAR({})", length).into(),
+            ColumnType::Bytes => "BYTEA".into(),
+            ColumnType::Date => "DATE".into(),
+            ColumnType::Timestamp => "TIMESTAMP".into(),
+            ColumnType::TimestampTz => "TIMESTAMPTZ".into(),
+            ColumnType::Uuid => "UUID".into(),
+            ColumnType::Json => "JSONB".into(),
+            ColumnType::Custom(sql) => sql.clone().into(),
+        }
+    }
+}
+
+#[cfg(feature = "mysql")]
+impl TableDialect for sqlx::MySql {
+    const IDENTIFIER_QUOTE: char = '`';
+
+    fn column_type(column_type: &ColumnType) -> Cow<'static, str> {
+        match column_type {
+            ColumnType::SmallInt => "SMALLINT".into(),
+            ColumnType::Integer => "INT".into(),
+            ColumnType::BigInt => "BIGINT".into(),
+            ColumnType::Serial => "INT AUTO_INCREMENT".into(),
+            ColumnType::BigSerial => "BIGINT AUTO_INCREMENT".into(),
+            ColumnType::Boolean => "BOOLEAN".into(),
+            ColumnType::Real => "FLOAT".into(),
+            ColumnType::Double => "DOUBLE".into(),
+            ColumnType::Numeric(precision, scale) => {
+                format!("DECIMAL({}, {})", precision, scale).into()
+            }
+            ColumnType::Text => "TEXT".into(),
+            ColumnType::Varchar(length) => format!("VARCHAR({})", length).into(),
+            ColumnType::Bytes => "BLOB".into(),
+            ColumnType::Date => "DATE".into(),
+            ColumnType::Timestamp => "DATETIME".into(),
+            ColumnType::TimestampTz => "TIMESTAMP".into(),
+            ColumnType::Uuid => "CHAR(36)".into(),
+            ColumnType::Json => "JSON".into(),
+            ColumnType::Custom(sql) => sql.clone().into(),
+        }
+    }
+}
+
+/// SQLite only has a handful of storage classes, and an `INTEGER PRIMARY KEY`
+/// column is auto-incrementing
+#[cfg(feature = "sqlite")]
+impl TableDialect for sqlx::Sqlite {
+    fn column_type(column_type: &ColumnType) -> Cow<'static, str> {
+        match column_type {
+            ColumnType::SmallInt
+            | ColumnType::Integer
+            | ColumnType::BigInt
+            | ColumnType::Serial
+            | ColumnType::BigSerial => "INTEGER".into(),
+            ColumnType::Boolean => "BOOLEAN".into(),
+            ColumnType::Real | ColumnType::Double => "REAL".into(),
+            ColumnType::Numeric(..) => "NUMERIC".into(),
+            ColumnType::Text | ColumnType::Varchar(_) => "TEXT".into(),
+            ColumnType::Bytes => "BLOB".into(),
+            ColumnType::Date
+            | ColumnType::Timestamp
+            | ColumnType::TimestampTz
+            | ColumnType::Uuid
+            | ColumnType::Json => "TEXT".into(),
+            ColumnType::Custom(sql) => sql.clone().into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn quotes_identifiers() {
+        use sqlx::Postgres;
+
+        assert_eq!(quote::<Postgres>("users"), r#""users""#);
+        assert_eq!(quote::<Postgres>("app.users"), r#""app"."users""#);
+        assert_eq!(quote::<Postgres>(r#"say "hi""#), r#""say ""hi""""#);
+        assert_eq!(
+            table("app.users").down::<Postgres>(),
+            r#"DROP TABLE "app"."users""#
+        );
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn places_not_null_before_default() {
+        let sql = table("users")
+            .column("id", ColumnType::BigSerial)
+            .nullable_column("email", ColumnType::Varchar(255))
+            .column("status", ColumnType::Text)
+            .default("'pending'")
+            .nullable_column("seen_at", ColumnType::TimestampTz)
+            .default("now()")
+            .up::<sqlx::Postgres>();
+        assert_eq!(
+            sql,
+            "CREATE TABLE \"users\" (\n    \
+             \"id\" BIGSERIAL NOT NULL,\n    \
+             \"email\" VARCHAR(255),\n    \
+             \"status\" TEXT NOT NULL DEFAULT 'pending',\n    \
+             \"seen_at\" TIMESTAMPTZ DEFAULT now()\n\
+             )"
+        );
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn adds_constraints_after_the_columns() {
+        let sql = table("app.orders")
+            .column("id", ColumnType::BigInt)
+            .column("user_id", ColumnType::BigInt)
+            .column("number", ColumnType::Integer)
+            .primary_key(&["id"])
+            .unique(&["user_id", "number"])
+            .foreign_key(&["user_id"], "app.users", &["id"])
+            .up::<sqlx::Postgres>();
+        assert_eq!(
+            sql,
+            "CREATE TABLE \"app\".\"orders\" (\n    \
+             \"id\" BIGINT NOT NULL,\n    \
+             \"user_id\" BIGINT NOT NULL,\n    \
+             \"number\" INTEGER NOT NULL,\n    \
+             PRIMARY KEY (\"id\"),\n    \
+             UNIQUE (\"user_id\", \"number\"),\n    \
+             FOREIGN KEY (\"user_id\") REFERENCES \"app\".\"users\" (\"id\")\n\
+             )"
+        );
+    }
+
+    #[cfg(feature = "mysql")]
+    #[test]
+    fn quotes_mysql_identifiers_with_backticks() {
+        use sqlx::MySql;
+
+        assert_eq!(quote::<MySql>("app.users"), "`app`.`users`");
+        assert_eq!(quote::<MySql>("a`b"), "`a``b`");
+        assert_eq!(
+            table("users")
+                .column("id", ColumnType::BigSerial)
+                .primary_key(&["id"])
+                .up::<MySql>(),
+            "CREATE TABLE `users` (\n    `id` BIGINT AUTO_INCREMENT NOT NULL,\n    PRIMARY KEY (`id`)\n)"
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn maps_column_types_to_sqlite_storage_classes() {
+        use sqlx::Sqlite;
+
+        assert_eq!(Sqlite::column_type(&ColumnType::BigSerial), "INTEGER");
+        assert_eq!(Sqlite::column_type(&ColumnType::Varchar(10)), "TEXT");
+        assert_eq!(Sqlite::column_type(&ColumnType::Numeric(10, 2)), "NUMERIC");
+    }
+}
@@ -0,0 +1,84 @@
+use super::{is_builtin, Migration, MigratorConfig};
+use std::collections::HashSet;
+
+#[derive(Clone, Debug, Default)]
+/// Selects which pending migrations are performed by their tags. The default
+/// filter selects every migration.
+pub struct TagFilter {
+    /// When not empty, only migrations with at least one of these tags are
+    /// performed
+    pub include: Vec<String>,
+    /// Migrations with any of these tags aren't performed
+    pub exclude: Vec<String>,
+}
+
+impl TagFilter {
+    /// Only perform migrations tagged with `tag`
+    pub fn include(tag: &str) -> Self {
+        TagFilter {
+            include: vec![tag.to_owned()],
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Perform every migration except those tagged with `tag`
+    pub fn exclude(tag: &str) -> Self {
+        TagFilter {
+            include: Vec::new(),
+            exclude: vec![tag.to_owned()],
+        }
+    }
+
+    /// Returns true if `migration` is selected by this filter
+    pub fn matches(&self, migration: &Migration) -> bool {
+        let tagged = |tags: &[String]| tags.iter().any(|tag| migration.tags.contains(tag));
+        (self.include.is_empty() || tagged(&self.include)) && !tagged(&self.exclude)
+    }
+}
+
+/// The tag of migrations that destroy data, such as by dropping a column
+pub(crate) const DESTRUCTIVE: &str = "destructive";
+
+/// The tag of migrations performed after a deploy, which only
+/// `Migrator::run_post_deploy` and `Migrator::run` perform
+pub(crate) const POST_DEPLOY: &str = "post_deploy";
+
+impl Migration {
+    /// Tag the migration, such as with `slow` or `data`, so that runs can
+    /// include or exclude it with a `TagFilter`
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_owned());
+        self
+    }
+
+    /// Tag the migration as `destructive`, such as when it drops a column, so
+    /// that the confirmation callback is asked before it's performed
+    pub fn destructive(self) -> Self {
+        self.tag(DESTRUCTIVE)
+    }
+
+    /// Assign the migration to the post-deploy phase, such as when it drops
+    /// a column only the previous release reads, so that it's left pending
+    /// by `Migrator::run_pre_deploy`. Migrations are pre-deploy by default.
+    pub fn post_deploy(self) -> Self {
+        self.tag(POST_DEPLOY)
+    }
+
+    pub(crate) fn is_post_deploy(&self) -> bool {
+        self.tags.iter().any(|tag| tag == POST_DEPLOY)
+    }
+}
+
+/// Removes the pending migrations that `config.tag_filter` doesn't select.
+/// Performed migrations are kept, so their checksums are still verified.
+pub(crate) fn filter(
+    migrations: &mut Vec<Migration>,
+    performed_migrations: &HashSet<String>,
+    config: &MigratorConfig,
+) {
+    migrations.retain(|migration| {
+        is_builtin(&migration.name)
+            || performed_migrations.contains(&migration.name)
+            || config.tag_filter.matches(migration)
+    });
+}
@@ -0,0 +1,121 @@
+use super::Migration;
+use sqlx::{Executor, PgConnection, Postgres};
+use std::sync::{Arc, Mutex};
+
+/// Returns the views and materialized views depending on the table `$1`, or
+/// only on its column `$2` when bound, including views depending on those
+/// views. Each view is ordered after the views it depends on.
+const SELECT_DEPENDENT_VIEWS: &str = r#"
+    WITH RECURSIVE dependents AS (
+        SELECT DISTINCT r.ev_class AS oid, 1 AS depth
+        FROM pg_depend d JOIN pg_rewrite r ON r.oid = d.objid
+        WHERE d.classid = 'pg_rewrite'::regclass AND d.refclassid = 'pg_class'::regclass
+            AND d.refobjid = to_regclass($1) AND r.ev_class <> d.refobjid
+            AND ($2::TEXT IS NULL OR d.refobjsubid = (
+                SELECT attnum FROM pg_attribute WHERE attrelid = to_regclass($1) AND attname = $2
+            ))
+        UNION ALL
+        SELECT r.ev_class, dependents.depth + 1
+        FROM dependents
+        JOIN pg_depend d ON d.refobjid = dependents.oid
+            AND d.classid = 'pg_rewrite'::regclass AND d.refclassid = 'pg_class'::regclass
+        JOIN pg_rewrite r ON r.oid = d.objid
+        WHERE r.ev_class <> dependents.oid
+    )
+    SELECT format('%I.%I', n.nspname, c.relname) AS name, c.relkind = 'm' AS materialized,
+        pg_get_viewdef(c.oid) AS definition, array_to_string(c.reloptions, ', ') AS options
+    FROM dependents JOIN pg_class c ON c.oid = dependents.oid
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    GROUP BY c.oid, n.nspname, c.relname, c.relkind, c.reloptions
+    ORDER BY max(dependents.depth)
+    "#;
+
+/// A view dropped before a migration's body and recreated after it
+struct DependentView {
+    name: String,
+    materialized: bool,
+    definition: String,
+    options: Option<String>,
+}
+
+impl DependentView {
+    fn kind(&self) -> &'static str {
+        if self.materialized {
+            "MATERIALIZED VIEW"
+        } else {
+            "VIEW"
+        }
+    }
+
+    fn create(&self) -> String {
+        let options = match &self.options {
+            Some(options) => format!(" WITH ({})", options),
+            None => String::new(),
+        };
+        format!(
+            "CREATE {} {}{} AS {}",
+            self.kind(),
+            self.name,
+            options,
+            self.definition.trim().trim_end_matches(';')
+        )
+    }
+}
+
+impl Migration {
+    /// Wrap the up statements added by `body` with Rust code that drops the
+    /// Postgres views depending on `table`, or only on its `column` when
+    /// given, and recreates them afterwards, such as when changing the type of
+    /// a column used by a view. Views depending on those views are rebuilt
+    /// too. The views' definitions are read when the migration executes. Down
+    /// statements added by `body` aren't wrapped, and privileges granted on
+    /// the views aren't restored.
+    pub fn rebuilding_dependent_views<F>(self, table: &str, column: Option<&str>, body: F) -> Self
+    where
+        F: FnOnce(Migration) -> Migration,
+    {
+        let views = Arc::new(Mutex::new(Vec::<DependentView>::new()));
+        let (table, column) = (table.to_owned(), column.map(str::to_owned));
+        let dropped = views.clone();
+        let migration = self.with_up_fn::<Postgres, PgConnection, _>(move |conn| {
+            let (table, column, dropped) = (table.clone(), column.clone(), dropped.clone());
+            Box::pin(async move {
+                let found = sqlx::query_as::<_, (String, bool, String, Option<String>)>(
+                    SELECT_DEPENDENT_VIEWS,
+                )
+                .bind(&table)
+                .bind(&column)
+                .fetch_all(&mut *conn)
+                .await?
+                .into_iter()
+                .map(|(name, materialized, definition, options)| DependentView {
+                    name,
+                    materialized,
+                    definition,
+                    options,
+                })
+                .collect::<Vec<_>>();
+                for view in found.iter().rev() {
+                    conn.execute(format!("DROP {} {}", view.kind(), view.name).as_str())
+                        .await?;
+                }
+                *dropped.lock().unwrap() = found;
+                Ok(())
+            })
+        });
+        body(migration).with_up_fn::<Postgres, PgConnection, _>(move |conn| {
+            let recreate = views
+                .lock()
+                .unwrap()
+                .drain(..)
+                .map(|view| view.create())
+                .collect::<Vec<_>>();
+            Box::pin(async move {
+                for statement in recreate {
+                    conn.execute(statement.as_str()).await?;
+                }
+                Ok(())
+            })
+        })
+    }
+}